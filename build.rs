@@ -3,4 +3,5 @@ fn main() {
     println!("cargo:rustc-link-lib=SDL2");
     println!("cargo:rustc-link-lib=SDL2_ttf");
     println!("cargo:rustc-link-lib=SDL2_image");
+    println!("cargo:rustc-link-lib=SDL2_mixer");
 }
\ No newline at end of file
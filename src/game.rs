@@ -1,13 +1,48 @@
-use std::path::Path;
-
-use sdl2::{image::LoadTexture, keyboard::Keycode, rect::{FPoint, Point}, render::{Canvas, TextureCreator, WindowCanvas}, video::WindowContext, EventPump, Sdl};
+use sdl2::{keyboard::Keycode, rect::{FPoint, FRect, Point, Rect}, render::{TextureCreator, WindowCanvas}, video::WindowContext, EventPump, GameControllerSubsystem};
 use sdl2::video::Window;
 use sdl2::event::Event;
 use sdl2::pixels::Color;
-use sdl2::ttf::Font;
+use sdl2::ttf::Sdl2TtfContext;
+use rand::Rng;
 
 use crate::modules::{Bullet, Camera, Enemy, Entity, EntityType, GameObject, Player, ResourceManager, Utils, Damageable,
-EnemySpawner};
+EnemySpawner, Hud, HudAnchor, HudWidget, VideoSettings, GameSettings, HitStop,
+ParticleEmitterConfig, ParticleSystem, AudioManager, InputState,
+InputAction, InputBindings, SettingsMenu, DamageNumbers, DecalLayer, EnemyDeath, Pickup, PickupKind, ComboCounter, WaveManager, WaveState, HighScore,
+DifficultyDirector, DirectorConfig, Boss, BulletExplosion, DamageInfo, DamageType, LEVEL_SPAWN_MARGIN, Pool, PoolStats, allocation_count};
+
+// font usati per l'HUD: (nome nel resource manager, dimensione). Il testo viene disegnato alla sua dimensione
+// reale, quindi la dimensione del font e' quella che si vede a schermo
+const HUD_FONT:(&str, u16) = ("hud", 28);
+const HUD_TITLE_FONT:(&str, u16) = ("hud", 72);
+const DAMAGE_NUMBER_FONT:(&str, u16) = ("hud", 20);
+const DAMAGE_NUMBER_CAPACITY:usize = 256;
+const DEBUG_FONT:(&str, u16) = DAMAGE_NUMBER_FONT; // stesso font gia' caricato per i numeri del danno
+const MENU_FONT:(&str, u16) = DAMAGE_NUMBER_FONT; // le righe del menu di pausa sono tante, si usa il font piccolo
+const BULLET_POOL_CAPACITY:usize = 256; // bullet creati all'avvio, il pool cresce se non bastano
+const DECAL_CAPACITY:usize = 64; // corpi + macchie di sangue a terra
+const HUD_PADDING:i32 = 10;
+const PARTICLE_CAPACITY:usize = 2048;
+const BINDINGS_PATH:&str = "bindings.cfg";
+const HIGH_SCORE_PATH:&str = "highscore.cfg";
+pub const SETTINGS_PATH:&str = "settings.cfg";
+const PLAYER_HEALTH_BAR_SIZE:(u32, u32) = (200, 18);
+const DASH_BAR_SIZE:(u32, u32) = (120, 8);
+const COMBO_BAR_SIZE:(u32, u32) = (140, 6);
+const BOSS_HEALTH_BAR_SIZE:(u32, u32) = (400, 14);
+const AMMO_ICON_SIZE:(u32, u32) = (40, 20); // missile.png e' 100x50
+const BOSS_SCORE_INTERVAL:i32 = 1000; // modalita' infinita: un boss ogni 1000 punti
+const BOSS_WAVE_INTERVAL:u32 = 5; // modalita' ondate: un boss ogni 5 ondate
+const BOSS_ZOOM:f32 = 0.8; // la camera si allontana durante lo scontro
+const LEVEL_RECT:(f32, f32, f32, f32) = (-800.0, -600.0, 1600.0, 1200.0); // (x, y, w, h) dell'area giocabile in world space, muri esterni compresi
+const LEVEL_WALL_THICKNESS:f32 = 40.0;
+
+// configurazione preparata in main prima di creare il gioco, passata tutta insieme a Game::new
+pub struct GameConfig{
+    pub video_settings:VideoSettings,
+    pub settings:GameSettings,
+    pub audio:AudioManager,
+}
 
 pub struct Game<'l>{
     canvas: &'l mut WindowCanvas,
@@ -15,24 +50,73 @@ pub struct Game<'l>{
     resource_manager: ResourceManager<'l>,
     player: Player,
     gameobjects:Vec<Box<dyn GameObject>>,
+    bullets:Pool<Bullet>, // i bullet non stanno nei gameobjects ma vengono riciclati dal pool
     main_camera:Camera,
     enemy_spawner:EnemySpawner,
+    director:DifficultyDirector,
+    boss:Option<Boss>,
+    bosses_defeated:u32,
+    next_boss_score:i32,
+    last_boss_wave:u32,
+    zoom_before_boss:f32,
     game_score:i32,
-    last_score_checkpoint: i32, // nuova variabile per evitare che la difficolta' incrementi quando lo score e' fermo a 50
+    combo:ComboCounter,
+    waves:WaveManager,
+    high_score:HighScore,
+    run_recorded:bool, // record della partita gia' salvato (game over)
+    new_record:bool,
 
     utils:Utils,
+    hud:Hud,
+    video_settings:VideoSettings,
+    settings:GameSettings,
+    hit_stop:HitStop,
+    particles:ParticleSystem,
+    damage_numbers:DamageNumbers,
+    decals:DecalLayer,
+    audio:AudioManager,
+    input:InputState,
+    settings_menu:SettingsMenu,
+    debug_overlay:bool, // statistiche dei pool, allocazioni e director (InputAction::ToggleDebug)
+    last_allocation_count:Option<usize>,
 }
 
 impl<'l> Game<'l>{
     // ritorno result in quanto per creare canvas ecc necessito di propagare l'errore, vale comunque come costruttore
-    pub fn new(canvas_main:&'l mut WindowCanvas, texture_creator:&'l TextureCreator<WindowContext>, event_pump_main:&'l mut EventPump) -> Result<Self, String>{
-        let mut resources = ResourceManager::new(texture_creator);
+    pub fn new(canvas_main:&'l mut WindowCanvas, texture_creator:&'l TextureCreator<WindowContext>, ttf_context:&'l Sdl2TtfContext, event_pump_main:&'l mut EventPump,
+        config:GameConfig, gamepad_subsystem:Option<GameControllerSubsystem>) -> Result<Self, String>{
+        let GameConfig { video_settings, settings, audio } = config;
+        video_settings.apply(canvas_main)?;
+
+        let mut resources = ResourceManager::new(texture_creator, ttf_context);
         
         // qui carico tutte le textures
         resources.load_texture("player", "assets/survivor_sheet.png").expect("Errore caricamento textures");
         resources.load_texture("default", "assets/spritesheet_characters.png").expect("Errore caricamento textures");
         resources.load_texture("bullet", "assets/missile.png").expect("Errore caricamento texture missile");
 
+        // texture delle particelle generata a runtime
+        let particle_texture = ParticleSystem::create_texture(&resources)?;
+        resources.insert_texture("particle", particle_texture);
+
+        // suoni e musica: se mancano i file o l'audio e' disattivato il gioco continua senza
+        if audio.is_enabled(){
+            for (sound_name, sound_path) in [("shoot", "assets/sounds/shoot.wav"), ("hit", "assets/sounds/hit.wav"),
+                ("death", "assets/sounds/death.wav"), ("pickup", "assets/sounds/pickup.wav")]{
+                if let Err(error) = resources.load_sound(sound_name, sound_path){
+                    println!("Errore caricamento suono {}: {}", sound_name, error);
+                }
+            }
+            if let Err(error) = resources.load_music("music", "assets/sounds/music.wav"){
+                println!("Errore caricamento musica: {}", error);
+            }
+        }
+
+        // font caricati una sola volta, poi si usano dal resource manager
+        resources.load_font(HUD_FONT.0, "fonts/Roboto_Condensed-Black.ttf", HUD_FONT.1)?;
+        resources.load_font(HUD_TITLE_FONT.0, "fonts/Roboto_Condensed-Black.ttf", HUD_TITLE_FONT.1)?;
+        resources.load_font(DAMAGE_NUMBER_FONT.0, "fonts/Roboto_Condensed-Black.ttf", DAMAGE_NUMBER_FONT.1)?;
+
         let mut player = Player::new("Player", 50.0, 100);
         player.player_entity.set_sprite(51, 43);
 
@@ -46,27 +130,101 @@ impl<'l> Game<'l>{
         base_enemy_2.enemy_entity.set_position(FPoint::new(-80.0, -80.0));
 
         let mut gameobjects_list:Vec<Box<dyn GameObject>> = Vec::new(); // si crea la lista di gameobjects
-        gameobjects_list.push(Box::new(base_enemy)); // si crea un puntatore e si MUOVE "other" nello heap (box puntera' a questo). Poi si mette il box nella lista
-        gameobjects_list.push(Box::new(base_enemy_2));
+        if !settings.wave_mode{ // nella modalita' ondate i nemici arrivano solo con le ondate
+            gameobjects_list.push(Box::new(base_enemy)); // si crea un puntatore e si MUOVE "other" nello heap (box puntera' a questo). Poi si mette il box nella lista
+            gameobjects_list.push(Box::new(base_enemy_2));
+        }
+
+        let high_score = HighScore::load(HIGH_SCORE_PATH).unwrap_or_else(|error| {
+            println!("Nessun record ({}: {})", HIGH_SCORE_PATH, error);
+            HighScore::new()
+        });
+
+        // comandi: se il file non esiste (o non si legge) si usano quelli di default e si crea il file
+        let bindings = InputBindings::load(BINDINGS_PATH).unwrap_or_else(|error| {
+            println!("Comandi di default ({}: {})", BINDINGS_PATH, error);
+            let default_bindings = InputBindings::new();
+            if let Err(error) = default_bindings.save(BINDINGS_PATH){
+                println!("Errore salvataggio comandi: {}", error);
+            }
+            default_bindings
+        });
 
         let enemy_spawn_rate_range = (4.0, 7.0);
         let enemy_health_range = (5, 10);
         let enemy_speed_range = (20.0, 25.0);
 
-        Ok(
-            Game { 
-                canvas: canvas_main, 
-                event_pump: event_pump_main,
-                resource_manager: resources,
-                player: player,
-                gameobjects: gameobjects_list,
-                utils: Utils::new(),
-                main_camera:Camera::new(),
-                enemy_spawner:EnemySpawner::new(enemy_spawn_rate_range, enemy_health_range, enemy_speed_range),
-                game_score:0,
-                last_score_checkpoint:0,
-            }
-        )
+        // muri della mappa (world space): bloccano movimento e proiettili
+        let mut utils = Utils::new();
+        utils.walls = vec![
+            FRect::new(-260.0, -200.0, 40.0, 180.0),
+            FRect::new(180.0, -220.0, 200.0, 40.0),
+            FRect::new(200.0, 140.0, 40.0, 200.0),
+            FRect::new(-300.0, 220.0, 220.0, 40.0),
+        ];
+        // muri esterni lungo il bordo del livello: il livello (e quindi il limite della camera) e' l'area che li contiene
+        let (level_x, level_y, level_w, level_h) = LEVEL_RECT;
+        utils.walls.extend([
+            FRect::new(level_x, level_y, level_w, LEVEL_WALL_THICKNESS),
+            FRect::new(level_x, level_y + level_h - LEVEL_WALL_THICKNESS, level_w, LEVEL_WALL_THICKNESS),
+            FRect::new(level_x, level_y, LEVEL_WALL_THICKNESS, level_h),
+            FRect::new(level_x + level_w - LEVEL_WALL_THICKNESS, level_y, LEVEL_WALL_THICKNESS, level_h),
+        ]);
+        let mut main_camera = Camera::new();
+        main_camera.set_bounds(utils.get_level_bounds());
+
+        let mut game = Game { 
+            canvas: canvas_main, 
+            event_pump: event_pump_main,
+            resource_manager: resources,
+            player,
+            gameobjects: gameobjects_list,
+            bullets: Pool::new(BULLET_POOL_CAPACITY, true, || Bullet::new(FPoint::new(1.0, 0.0), EntityType::Player, 0.0, FPoint::new(0.0, 0.0))),
+            utils,
+            hud: Hud::new(HUD_PADDING),
+            video_settings,
+            settings,
+            hit_stop: HitStop::new(true),
+            particles: ParticleSystem::new(PARTICLE_CAPACITY),
+            damage_numbers: DamageNumbers::new(DAMAGE_NUMBER_CAPACITY, DAMAGE_NUMBER_FONT),
+            decals: DecalLayer::new(DECAL_CAPACITY),
+            audio,
+            input: InputState::new(bindings, gamepad_subsystem),
+            settings_menu: SettingsMenu::new(MENU_FONT, HUD_TITLE_FONT),
+            main_camera,
+            enemy_spawner:EnemySpawner::new(enemy_spawn_rate_range, enemy_health_range, enemy_speed_range),
+            director:DifficultyDirector::new(DirectorConfig::new()),
+            boss:None,
+            bosses_defeated:0,
+            next_boss_score:BOSS_SCORE_INTERVAL,
+            last_boss_wave:0,
+            zoom_before_boss:1.0,
+            game_score:0,
+            combo:ComboCounter::new(),
+            waves:WaveManager::new(),
+            high_score,
+            run_recorded:false,
+            new_record:false,
+            debug_overlay:false,
+            last_allocation_count:allocation_count(),
+        };
+        game.apply_settings();
+
+        if let Err(error) = game.audio.play_music(&game.resource_manager, "music"){
+            println!("{}", error);
+        }
+
+        Ok(game)
+    }
+
+    // applica le impostazioni del giocatore ai vari sottosistemi (es. accessibilita' per shake e hit-stop)
+    pub fn apply_settings(&mut self){
+        self.main_camera.shake_enabled = self.settings.screen_shake;
+        self.hit_stop.enabled = self.settings.hit_stop;
+        self.player.aim_toggle_required = self.settings.aim_toggle_required;
+        self.audio.set_master_volume(self.settings.master_volume);
+        self.audio.set_sfx_volume(self.settings.sfx_volume);
+        self.audio.set_music_volume(self.settings.music_volume);
     }
 
     pub fn start(&mut self) -> Result<(), String>{ // Inizializzazione oggetti di base (come player ecc...)
@@ -83,10 +241,13 @@ impl<'l> Game<'l>{
     pub fn manage_events(&mut self) -> bool {
         
         for event in self.event_pump.poll_iter(){
-            self.player.move_player(&event);
-            self.player.player_controller(&event, &mut self.gameobjects);
-
             self.utils.utils_manage_events(&event);
+            self.input.manage_event(&event);
+
+            // con il menu aperto gli eventi vanno prima al menu (navigazione e rebinding)
+            if self.settings_menu.open && self.settings_menu.manage_event(&event, &mut self.input.bindings, &mut self.settings){
+                continue;
+            }
 
             match event{
                 Event::Quit {..} |
@@ -94,12 +255,56 @@ impl<'l> Game<'l>{
                     return false;
                 }
 
+                // rotella del mouse -> zoom della camera
+                Event::MouseWheel { y, ..} =>{
+                    self.main_camera.zoom_by_steps(y);
+                }
+
+                // F11 -> fullscreen on/off (repeat ignorato, altrimenti tenendo premuto si alterna di continuo)
+                Event::KeyDown { keycode: Some(Keycode::F11), repeat: false, ..} =>{
+                    if let Err(error) = self.video_settings.toggle_fullscreen(self.canvas){
+                        println!("Errore cambio fullscreen: {}", error);
+                    }
+                }
+
                 _ => {
                     continue;
                 }
             }
         }
 
+        // stato dei tasti tenuti premuti, letto dopo aver consumato tutti gli eventi del frame
+        self.input.update(self.event_pump);
+
+        // opzioni cambiate dal menu: si applicano subito e si salvano
+        if std::mem::take(&mut self.settings_menu.settings_changed){
+            self.apply_settings();
+            if let Err(error) = self.settings.save(SETTINGS_PATH){
+                println!("Errore salvataggio impostazioni: {}", error);
+            }
+        }
+
+        // se in questo frame e' stato assegnato un nuovo tasto non lo si usa anche come azione (es. rebinding di Pause)
+        if std::mem::take(&mut self.settings_menu.bindings_changed){
+            if let Err(error) = self.input.bindings.save(BINDINGS_PATH){
+                println!("Errore salvataggio comandi: {}", error);
+            }
+            return true;
+        }
+
+        if self.input.is_action_just_pressed(InputAction::Pause){
+            self.settings_menu.toggle();
+        }
+
+        if self.input.is_action_just_pressed(InputAction::ToggleDebug){
+            self.debug_overlay = !self.debug_overlay;
+        }
+
+        if !self.settings_menu.open{
+            self.player.move_player(&self.input);
+            self.player.player_controller(&self.input, &mut self.bullets, &mut self.particles, &mut self.audio);
+        }
+
         return true;
     }
 
@@ -107,6 +312,18 @@ impl<'l> Game<'l>{
         self.canvas.set_draw_color(Color::RGB(0, 0, 0)); // colore di sfondo
         self.canvas.clear(); // si imposta colore scelto
 
+        // rendering muri (sotto a tutto il resto)
+        self.canvas.set_draw_color(Color::RGB(90, 90, 100));
+        for wall in self.utils.walls.iter(){
+            let top_left = self.utils.world_to_screen(FPoint::new(wall.x, wall.y));
+            let wall_rect = Rect::new(top_left.x.round() as i32, top_left.y.round() as i32,
+                (wall.w * self.utils.main_camera_zoom).round() as u32, (wall.h * self.utils.main_camera_zoom).round() as u32);
+            self.canvas.fill_rect(wall_rect)?;
+        }
+
+        // corpi e sangue a terra, sotto player e nemici
+        self.decals.draw(self.canvas, &mut self.resource_manager, &self.utils)?;
+
         // rendering player
         // utilizzo game_utils in modo da portare tutti gli oggetti nel S.R della camera
         self.player.draw(self.canvas, self.resource_manager
@@ -116,76 +333,230 @@ impl<'l> Game<'l>{
         // rendering vari gameobjects
         if self.gameobjects.len() > 0{
             for game_object in self.gameobjects.iter_mut(){
-                if let Some(bullet) = game_object.as_any_mut().downcast_mut::<Bullet>(){
-                    let bullet_texture_scale_factor = 0.2;
-
-                    bullet.draw(self.canvas, self.resource_manager.get_texture("bullet").unwrap(), 
-                        0, &self.utils, bullet_texture_scale_factor).expect("Errore rendering bullet");
-                }else{
-                    game_object.draw(self.canvas, self.resource_manager
-                        .get_texture("default").unwrap(), 0, &self.utils, 1.0).expect("Errore renderizzando");
-                }
+                game_object.draw(self.canvas, self.resource_manager
+                    .get_texture("default").unwrap(), 0, &self.utils, 1.0).expect("Errore renderizzando");
             }
         }
 
-        // FONTS
-        // creo il ttf context. utilizzo map_err() in modo che, se da errore, invece di restituire
-        // Result<ttfContext, Error> restituisco Result<ttfContext, String>, questo permette di convertire
-        // l'errore in stringa facilmente. SI utilizza una funzione lambda, dove "e" rappresenta
-        // il tipo Error e io lo converto direttamente in stringa con e.to_string()
-        // ttf_context e' l'oggetto per gestire i font
-        let ttf_context = sdl2::ttf::init().map_err(|e| {e.to_string()})?;
-        // Path::new() restituisce &Path in quanto serve dimensione nota
-        let font_path:&Path = Path::new("fonts/Roboto_Condensed-Black.ttf");
-        let mut font = ttf_context.load_font(font_path, 128)?;
-        font.set_style(sdl2::ttf::FontStyle::NORMAL);
+        // bullet attivi del pool
+        let bullet_texture_scale_factor = 0.2;
+        for bullet in self.bullets.iter_mut(){
+            bullet.draw(self.canvas, self.resource_manager.get_texture("bullet").unwrap(),
+                0, &self.utils, bullet_texture_scale_factor).expect("Errore rendering bullet");
+        }
+
+        if let Some(boss) = self.boss.as_mut(){
+            boss.draw(self.canvas, self.resource_manager.get_texture("default").unwrap(), 0, &self.utils, 1.0)?;
+        }
+
+        // particelle sopra i gameobjects e sotto l'HUD
+        self.particles.draw(self.canvas, self.resource_manager.get_texture_mut("particle"), &self.utils)?;
+        self.damage_numbers.draw(self.canvas, &mut self.resource_manager, &self.utils)?;
 
         // stampa delle varie cose che devono essere stampate
         // N.B => Il testo rimane fisso nello schermo in quanto a spostarsi nella direzione opposta della camera
         // sono solo le entities (vedi impl Gameobject for Entities, qui nell'update si spostano le entities rispetto alla camera)
 
-        // stampa vita player:
-        let player_health_pos = Point::new(10, 10);
-        let player_health_size = Point::new(120, 40);
-        Utils::write_on_screen(format!("health: {}", self.player.get_current_health()).as_str(), self.canvas, &font, &self.resource_manager,
-        player_health_pos, player_health_size)?;
+        // barra della vita del player (in alto a sinistra) con il valore sotto
+        let health_bar = HudWidget::bar(PLAYER_HEALTH_BAR_SIZE, self.player.get_health_fraction(),
+            Color::RGB(200, 30, 30), Color::RGB(60, 60, 60))
+            .anchored(HudAnchor::TopLeft);
+        let health_bar_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &health_bar)?;
+
+        let health_label = HudWidget::label(format!("{} / {}", self.player.get_current_health().max(0), self.player.get_max_health()).as_str(),
+            HUD_FONT, Color::RGB(255, 255, 255))
+            .anchored(HudAnchor::TopLeft)
+            .offset(Point::new(0, health_bar_rect.height() as i32 + 4));
+        let health_label_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &health_label)?;
+
+        // ricarica del dash sotto la vita (piena = pronto)
+        let dash_color = if self.player.get_dash_charge() >= 1.0 { Color::RGB(80, 180, 255) } else { Color::RGB(60, 100, 140) };
+        let dash_bar = HudWidget::bar(DASH_BAR_SIZE, self.player.get_dash_charge(), dash_color, Color::RGB(60, 60, 60))
+            .anchored(HudAnchor::TopLeft)
+            .offset(Point::new(0, health_label_rect.bottom() - HUD_PADDING + 4));
+        let dash_bar_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &dash_bar)?;
+
+        // arma attuale sotto il dash
+        // con il potenziamento attivo si mostra anche il tempo rimasto
+        let weapon_text = match self.player.get_bullet_power(){
+            Some((power, time_left)) => format!("{} + {} {}s", self.player.weapon.get_name(), power.get_name(), time_left.ceil() as i32),
+            None => self.player.weapon.get_name().to_string(),
+        };
+        let weapon_label = HudWidget::label(weapon_text.as_str(), HUD_FONT, Color::RGB(200, 200, 200))
+            .anchored(HudAnchor::TopLeft)
+            .offset(Point::new(0, dash_bar_rect.bottom() - HUD_PADDING + 4));
+        self.hud.draw(self.canvas, &mut self.resource_manager, &weapon_label)?;
+
+        // stampa game score (in alto a destra, la posizione dipende dalla dimensione attuale del canvas)
+        let score_label = HudWidget::label(format!("Score : {}", self.game_score).as_str(), HUD_FONT, Color::RGB(255, 255, 255))
+            .anchored(HudAnchor::TopRight);
+        let score_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &score_label)?;
+
+        // valuta raccolta sotto lo score
+        let currency_label = HudWidget::label(format!("Coins : {}", self.player.currency).as_str(), HUD_FONT, Color::RGB(255, 215, 0))
+            .anchored(HudAnchor::TopRight)
+            .offset(Point::new(0, score_rect.height() as i32));
+        self.hud.draw(self.canvas, &mut self.resource_manager, &currency_label)?;
+
+        // colpi esplosivi (solo se presenti) in basso a destra: icona del missile a sinistra del numero
+        if self.player.explosive_ammo > 0{
+            let ammo_label = HudWidget::label(format!("x {}", self.player.explosive_ammo).as_str(), HUD_FONT, Color::RGB(255, 150, 40))
+                .anchored(HudAnchor::BottomRight);
+            let ammo_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &ammo_label)?;
+
+            // centrata in verticale rispetto al numero
+            let ammo_icon = HudWidget::icon("bullet", None, AMMO_ICON_SIZE)
+                .anchored(HudAnchor::BottomRight)
+                .offset(Point::new(-(ammo_rect.width() as i32 + 6), -((ammo_rect.height() as i32 - AMMO_ICON_SIZE.1 as i32) / 2)));
+            self.hud.draw(self.canvas, &mut self.resource_manager, &ammo_icon)?;
+        }
 
-        // stampa game score
+        // combo in alto al centro, con il tempo rimasto per continuarla
+        if self.combo.get_combo() > 1{
+            let combo_label = HudWidget::label(format!("Combo {}  x{:.1}", self.combo.get_combo(), self.combo.get_multiplier()).as_str(),
+                HUD_FONT, Color::RGB(255, 200, 60))
+                .anchored(HudAnchor::TopCenter);
+            let combo_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &combo_label)?;
+
+            let combo_bar = HudWidget::bar(COMBO_BAR_SIZE, self.combo.get_time_fraction(), Color::RGB(255, 200, 60), Color::RGB(60, 60, 60))
+                .anchored(HudAnchor::TopCenter)
+                .offset(Point::new(0, combo_rect.height() as i32 + 2));
+            self.hud.draw(self.canvas, &mut self.resource_manager, &combo_bar)?;
+        }
 
-        // prendo width ed height del canvas attuale
-        let score_pos = Point::new((self.canvas.output_size()?.0 - 150) as i32, 10 as i32);
-        let score_size = Point::new(120, 40);
-        Utils::write_on_screen(format!("Score : {}", self.game_score).as_str(), self.canvas, &font, &self.resource_manager, score_pos, score_size)?;
+        if self.settings.wave_mode{
+            self.draw_wave_hud()?;
+        }
+
+        // barra della vita del boss in basso, sopra al contatore delle ondate
+        if let Some(boss) = self.boss.as_ref(){
+            let boss_bar = HudWidget::bar(BOSS_HEALTH_BAR_SIZE, boss.get_health_fraction(), Color::RGB(170, 20, 170), Color::RGB(60, 60, 60))
+                .anchored(HudAnchor::BottomCenter)
+                .offset(Point::new(0, -40));
+            let boss_bar_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &boss_bar)?;
+
+            let boss_label = HudWidget::label(format!("BOSS  -  phase {}", boss.get_phase()).as_str(), HUD_FONT, Color::RGB(255, 255, 255))
+                .anchored(HudAnchor::BottomCenter)
+                .offset(Point::new(0, -40 - boss_bar_rect.height() as i32 - 2));
+            self.hud.draw(self.canvas, &mut self.resource_manager, &boss_label)?;
+        }
 
         if self.player.is_destroyed(){
-            let (canvas_width, canvas_height) = self.canvas.output_size()?;
-            let text = "GAME OVER";
-
-            let text_size = Point::new(300, 80);
-            let text_pos = Point::new(
-                (canvas_width / 2) as i32 - (text_size.x / 2),
-                (canvas_height / 2) as i32 - (text_size.y / 2),
-            );
-        
-            Utils::write_on_screen(
-                text,
-                self.canvas,
-                &font,
-                &self.resource_manager,
-                text_pos,
-                text_size,
-            )?;
+            let game_over_label = HudWidget::label("GAME OVER", HUD_TITLE_FONT, Color::RGB(255, 255, 255))
+                .anchored(HudAnchor::Center);
+            let game_over_rect = self.hud.draw(self.canvas, &mut self.resource_manager, &game_over_label)?;
+
+            let record_text = if self.new_record{
+                String::from("New record!")
+            }else if self.settings.wave_mode{
+                format!("Best : {} (wave {})", self.high_score.best_wave_score, self.high_score.best_wave)
+            }else{
+                format!("Best : {}", self.high_score.best_score)
+            };
+            let record_label = HudWidget::label(record_text.as_str(), HUD_FONT, Color::RGB(255, 215, 0))
+                .anchored(HudAnchor::Center)
+                .offset(Point::new(0, game_over_rect.height() as i32));
+            self.hud.draw(self.canvas, &mut self.resource_manager, &record_label)?;
+        }
+
+        if self.debug_overlay{
+            self.draw_debug_overlay()?;
+        }
+
+        if self.settings_menu.open{
+            self.settings_menu.draw(self.canvas, &mut self.resource_manager, &self.hud, &self.input.bindings, &self.settings)?;
         }
 
         self.canvas.present(); // si renderizza canvas
+        self.resource_manager.end_frame(); // si liberano i testi non piu' usati (es. vecchio score)
+        Ok(())
+    }
+
+    // statistiche in basso a sinistra, dal basso verso l'alto
+    fn draw_debug_overlay(&mut self) -> Result<(), String>{
+        // allocazioni fatte dall'ultimo frame (compreso il testo di questo overlay)
+        let allocations = allocation_count();
+        let allocations_text = match (allocations, self.last_allocation_count){
+            (Some(allocations), Some(last_allocations)) => (allocations - last_allocations).to_string(),
+            _ => String::from("n/a (feature alloc-counter)"),
+        };
+        self.last_allocation_count = allocations;
+
+        let pool_line = |name:&str, stats:PoolStats| format!("{} : {} / {}  (peak {}, alloc {}, reused {})",
+            name, stats.active, stats.capacity, stats.peak_active, stats.allocations, stats.reuses);
+        let mut lines = vec![
+            format!("Heap allocations / frame : {}", allocations_text),
+            format!("Gameobjects : {}", self.gameobjects.len()),
+            pool_line("Bullets", self.bullets.get_stats()),
+            pool_line("Particles", self.particles.get_pool_stats()),
+            pool_line("Damage numbers", self.damage_numbers.get_pool_stats()),
+        ];
+        if !self.settings.wave_mode{
+            lines.push(format!("Director : {:?}  intensity {:.2}", self.director.get_phase(), self.director.get_intensity()));
+        }
+
+        let mut offset = 0;
+        for line in lines.iter(){
+            let label = HudWidget::label(line.as_str(), DEBUG_FONT, Color::RGB(120, 255, 120))
+                .anchored(HudAnchor::BottomLeft)
+                .offset(Point::new(0, -offset));
+            offset += self.hud.draw(self.canvas, &mut self.resource_manager, &label)?.height() as i32;
+        }
+
+        Ok(())
+    }
+
+    // contatore delle ondate in basso, banner all'inizio di ogni ondata e countdown durante l'intervallo
+    fn draw_wave_hud(&mut self) -> Result<(), String>{
+        let white = Color::RGB(255, 255, 255);
+
+        if self.waves.get_wave() > 0{
+            let counter_label = HudWidget::label(format!("Wave {}  -  Enemies : {} / {}", self.waves.get_wave(), self.waves.get_remaining_enemies(), self.waves.get_enemy_count()).as_str(),
+                HUD_FONT, white)
+                .anchored(HudAnchor::BottomCenter);
+            self.hud.draw(self.canvas, &mut self.resource_manager, &counter_label)?;
+        }
+
+        let banner_alpha = self.waves.get_banner_alpha();
+        if banner_alpha > 0{
+            let banner = HudWidget::label(format!("Wave {}", self.waves.get_wave()).as_str(), HUD_TITLE_FONT,
+                Color::RGBA(255, 255, 255, banner_alpha))
+                .anchored(HudAnchor::Center);
+            self.hud.draw(self.canvas, &mut self.resource_manager, &banner)?;
+        }
+
+        if let WaveState::Intermission(time_left) = self.waves.get_state(){
+            let mut rect_height = 0;
+            if let Some((wave, bonus)) = self.waves.get_last_clear(){
+                let clear_label = HudWidget::label(format!("Wave {} cleared!  +{}", wave, bonus).as_str(), HUD_FONT, Color::RGB(255, 215, 0))
+                    .anchored(HudAnchor::Center);
+                rect_height = self.hud.draw(self.canvas, &mut self.resource_manager, &clear_label)?.height() as i32;
+            }
+
+            let countdown_label = HudWidget::label(format!("Next wave in {}", time_left.ceil() as i32).as_str(), HUD_FONT, white)
+                .anchored(HudAnchor::Center)
+                .offset(Point::new(0, rect_height));
+            self.hud.draw(self.canvas, &mut self.resource_manager, &countdown_label)?;
+        }
+
         Ok(())
     }
 
     pub fn update(&mut self, deltatime:f32){
 
+        // il gioco e' fermo se il player e' morto o se il menu di pausa e' aperto
         if self.player.is_destroyed(){
+            self.record_high_score();
             return;
         }
+        if self.settings_menu.open{
+            return;
+        }
+
+        // FEEDBACK : shake e hit-stop usano il deltatime reale, tutto il resto quello scalato dall'hit-stop
+        self.hit_stop.update(deltatime);
+        self.main_camera.update_shake(deltatime);
+        let deltatime = deltatime * self.hit_stop.get_time_scale();
 
         // non posso passare come parametro &game in quanto avrei in contemporanea un riferimento mutabile (&mut self)
         // e uno immutabile (quello che voglio passare come parametro ad update)
@@ -193,6 +564,17 @@ impl<'l> Game<'l>{
         // SETTAGGIO UTILS
         self.utils.save_player_position(self.player.player_entity.get_position()); //Salvo in utils la posizione del player
         self.utils.main_camera_position = self.main_camera.get_main_camera_position(); // salvo in utils la posizione della camera
+        self.utils.main_camera_rotation = self.main_camera.get_main_camera_rotation();
+        self.utils.main_camera_zoom = self.main_camera.get_zoom();
+        self.utils.gamepad_aim = self.input.get_gamepad_aim();
+        self.utils.screen_size = Utils::view_size(self.canvas).unwrap_or(self.utils.screen_size); // dimensione attuale della vista
+        self.utils.enemy_positions.clear(); // il vec viene riutilizzato ogni frame
+        self.utils.enemy_positions.extend(self.gameobjects.iter()
+            .filter_map(|game_object| game_object.as_any().downcast_ref::<Enemy>())
+            .map(|enemy| enemy.get_entity().get_position()));
+        if let Some(boss) = self.boss.as_ref(){
+            self.utils.enemy_positions.push(boss.get_entity().get_position());
+        }
         // in modo da poterla usare negli update dei vari gameobjects
 
         // CAMERA : Spostamento in base a posizione del player
@@ -201,6 +583,10 @@ impl<'l> Game<'l>{
         // UPDATE DEI GAMEOBEJCTS
 
         self.player.update(deltatime, &self.utils);
+        self.particles.update(deltatime);
+        self.damage_numbers.update(deltatime);
+        self.decals.update(deltatime);
+        self.combo.update(deltatime);
 
 
         // eseguire l'update di tutti gli altri gameobjects
@@ -213,6 +599,25 @@ impl<'l> Game<'l>{
             }
         }
 
+        for bullet in self.bullets.iter_mut(){
+            bullet.update(deltatime, &self.utils);
+        }
+
+        if let Some(boss) = self.boss.as_mut(){
+            boss.update(deltatime, &self.utils);
+            boss.damage_player(&mut self.player);
+            self.gameobjects.extend(boss.take_spawns()); // nemici evocati
+            boss.fire_bullets(&mut self.bullets);
+        }
+
+        // feedback quando il player viene colpito
+        let recent_damage = self.player.take_recent_damage();
+        if recent_damage > 0{
+            self.main_camera.add_trauma(0.5);
+            self.hit_stop.trigger(0.08, 0.05);
+            self.audio.play_at("hit", self.player.player_entity.get_position());
+        }
+
         // questo metodo per rimuovere bullet da Vec non funziona in rust in quanto remove() e' mut ref
         // quindi non posso avere altri riferimenti (anche se non mutabili) a gameobjects insieme
         // for (index, game_object) in self.gameobjects.iter_mut().enumerate(){
@@ -221,6 +626,9 @@ impl<'l> Game<'l>{
         //     }
         // }
         // quello che posso fare e' usare il metodo retain:
+        let mut dead_enemies:Vec<EnemyDeath> = Vec::new();
+        let mut collected_pickups:Vec<(PickupKind, FPoint)> = Vec::new();
+        let mut explosions:Vec<BulletExplosion> = Vec::new();
         self.gameobjects.retain(|game_object| {
             // vecchie implementazioni : 
 
@@ -234,11 +642,15 @@ impl<'l> Game<'l>{
             //     true // tengo tutti gli altri
             // }
             
-            // implementazione che incrementa lo score:
+            // implementazione attuale: dei nemici morti si salvano le informazioni per gli effetti di morte,
+            // gestiti dopo in on_enemy_death (nella closure non si puo' chiamare un metodo di self)
 
             if game_object.is_destroyed(){ // se il gameobject e' destroyed
                 if let Some(enemy) = game_object.as_any().downcast_ref::<Enemy>(){
-                    self.game_score += 10; // se e' un enemy in particolare, prima di rimuoverlo incremento score
+                    dead_enemies.push(enemy.get_death_info());
+                }else if let Some(pickup) = game_object.as_any().downcast_ref::<Pickup>()
+                    && pickup.is_collected(){ // i pickup scaduti spariscono senza effetto
+                    collected_pickups.push((pickup.kind, pickup.get_position()));
                 }
                 false // ritorno false quindi lo tolgo dalla lista
             }else{
@@ -246,6 +658,32 @@ impl<'l> Game<'l>{
             }
         });
 
+        // i bullet distrutti tornano nel pool invece di essere deallocati
+        self.bullets.retain(|bullet| {
+            if bullet.is_destroyed(){
+                if let Some(explosion) = bullet.get_explosion(){ // proiettili esplosivi
+                    explosions.push(*explosion);
+                }
+                false
+            }else{
+                true
+            }
+        });
+
+        // le esplosioni vengono applicate prima degli effetti di morte: i nemici uccisi qui vengono rimossi al prossimo frame
+        for explosion in explosions.iter(){
+            self.apply_explosion(explosion);
+        }
+
+        for death in dead_enemies.iter(){
+            self.on_enemy_death(death);
+        }
+
+        for (kind, position) in collected_pickups{
+            self.player.collect_pickup(kind);
+            self.audio.play_at("pickup", position);
+        }
+
         // println!("{}", self.gameobjects.len());
 
         // SPAWN ENEMIES
@@ -261,7 +699,14 @@ impl<'l> Game<'l>{
         // }
 
         // metodo che permette di spawnare piu' nemici alla volta
-        let new_enemies = self.enemy_spawner.spawn_enemies(deltatime, &self.utils);
+        // nella modalita' ondate invece i nemici li decide il WaveManager
+        let new_enemies = if self.settings.wave_mode{
+            let enemies_alive = self.gameobjects.iter().filter(|game_object| game_object.as_any().is::<Enemy>()).count()
+                + self.boss.is_some() as usize;
+            self.waves.update(deltatime, enemies_alive, &mut self.enemy_spawner, &self.utils)
+        }else{
+            self.enemy_spawner.spawn_enemies(deltatime, &self.utils)
+        };
         match new_enemies {
             Some(new_enemies) => {
                 for enemy in new_enemies{ // voglio che vengano consumati, quindi senza iter
@@ -275,42 +720,194 @@ impl<'l> Game<'l>{
 
         // COLLISIONE BULLETS
 
-        // per collisione bullet
-        // devo creare due liste separte perche non posso fare due cicli annidati entrambi con borrow mutabile su gameobjects
-        // quindi metto bullet ed enemies in un vec e poi ciclo su questi dopo
-
+        // i bullet sono nel pool, quindi basta raccogliere i nemici dai gameobjects.
         // N.B per ora non sto sfruttando a pieno il metodo generico bullet.damage_enemy!
-        // spostare eventualmente queste liste come metodi generali di Game.rs
-        let mut bullets = vec![];
-        let mut enemies = vec![];
-
-        for obj in self.gameobjects.iter_mut() {
-            // utilizzo il match in modo da evitare di avere due as_any_mut() nello stesso ciclo
-            // questo causa problemi con il borrow checker in quanto avrei 2 riferimenti mutabili &mut dyn Any
-            // in questo modo ne ho solo uno e metto nella lista sulla base della struct che implementa il tratto gameobject
-            match obj.as_any_mut() {
-                any if any.is::<Bullet>() => {
-                    let bullet = any.downcast_mut::<Bullet>().unwrap();
-                    bullets.push(bullet);
-                },
-                any if any.is::<Enemy>() => {
-                    let enemy = any.downcast_mut::<Enemy>().unwrap();
-                    enemies.push(enemy);
-                },
-                _ => {}
+        let mut enemies:Vec<&mut Enemy> = self.gameobjects.iter_mut()
+            .filter_map(|game_object| game_object.as_any_mut().downcast_mut::<Enemy>())
+            .collect();
+
+        for bullet in self.bullets.iter_mut(){
+            for enemy in &mut enemies {
+                bullet.damage_enemy(*enemy, &mut self.particles, &mut self.audio, &mut self.damage_numbers);
+            }
+            // il metodo generico colpisce anche boss e player (i proiettili del boss), in base all'owner del bullet
+            if let Some(boss) = self.boss.as_mut(){
+                bullet.damage_enemy(boss, &mut self.particles, &mut self.audio, &mut self.damage_numbers);
             }
+            bullet.damage_enemy(&mut self.player, &mut self.particles, &mut self.audio, &mut self.damage_numbers);
         }
 
-        for bullet in &mut bullets {
-            for enemy in &mut enemies {
-                bullet.damage_enemy(*enemy);
+        // BOSS : morte e comparsa ai traguardi (score in modalita' infinita, ondate nella modalita' ondate)
+        if self.boss.as_ref().is_some_and(|boss| boss.is_destroyed())
+            && let Some(boss) = self.boss.take(){
+            self.on_boss_death(&boss);
+        }
+        self.check_boss_milestones();
+
+        // bonus per l'ondata completata
+        if let Some((_, bonus)) = self.waves.take_cleared_wave(){
+            self.game_score += bonus;
+            self.audio.play_at("pickup", self.player.player_entity.get_position());
+        }
+
+        // DIFFICOLTA' : nella modalita' infinita il director regola lo spawner (le ondate hanno la loro progressione)
+        if !self.settings.wave_mode{
+            self.director.update(deltatime, self.game_score, self.player.get_health_fraction(), recent_damage);
+            self.director.apply(&mut self.enemy_spawner);
+        }
+
+        // AUDIO : si riproducono i suoni richiesti durante il frame
+        self.audio.flush(&self.resource_manager, &self.utils);
+    }
+
+    // danno ad area a tutti i Damageable nel raggio (tranne quelli dello stesso tipo di chi ha sparato),
+    // decrescente dal centro verso il bordo
+    fn apply_explosion(&mut self, explosion:&BulletExplosion){
+        self.particles.emit(&ParticleEmitterConfig::explosion(), explosion.position, FPoint::new(1.0, 0.0));
+        self.audio.play_at("death", explosion.position);
+        self.main_camera.add_trauma(0.25);
+
+        let damage_at = |position:FPoint| -> Option<DamageInfo>{
+            let distance = Utils::calculate_point_distance(explosion.position, position);
+            if distance > explosion.radius{
+                return None;
+            }
+            let falloff = 1.0 - 0.5 * distance / explosion.radius;
+            Some(DamageInfo::new((explosion.damage as f32 * falloff).round() as i32, DamageType::Explosive, explosion.owner)
+                .with_direction(Utils::point_normalized(position - explosion.position))
+                .with_source_id(explosion.owner_id))
+        };
+
+        if explosion.owner != EntityType::Enemy{
+            for game_object in self.gameobjects.iter_mut(){
+                if let Some(enemy) = game_object.as_any_mut().downcast_mut::<Enemy>()
+                    && let Some(damage) = damage_at(enemy.get_entity().get_position()){
+                    let resolved = enemy.take_damage(&damage);
+                    self.damage_numbers.spawn(&resolved, enemy.get_entity().get_position());
+                }
+            }
+
+            if let Some(boss) = self.boss.as_mut()
+                && let Some(damage) = damage_at(boss.get_entity().get_position()){
+                let resolved = boss.take_damage(&damage);
+                self.damage_numbers.spawn(&resolved, boss.get_entity().get_position());
             }
         }
 
-        if self.game_score >= self.last_score_checkpoint + 50 {
-            self.enemy_spawner.increase_difficulty();
-            self.last_score_checkpoint = self.game_score;
+        if explosion.owner != EntityType::Player
+            && let Some(damage) = damage_at(self.player.player_entity.get_position()){
+            self.player.take_damage(&damage);
         }
+    }
 
+    fn check_boss_milestones(&mut self){
+        if self.boss.is_some(){
+            return;
+        }
+
+        let milestone_reached = if self.settings.wave_mode{
+            let wave = self.waves.get_wave();
+            let reached = wave > self.last_boss_wave && wave.is_multiple_of(BOSS_WAVE_INTERVAL) && self.waves.get_state() == WaveState::InProgress;
+            if reached{
+                self.last_boss_wave = wave;
+            }
+            reached
+        }else{
+            let reached = self.game_score >= self.next_boss_score;
+            if reached{
+                // prossimo multiplo sopra lo score attuale: se un bonus ha superato piu' traguardi insieme
+                // non restano traguardi arretrati che farebbero comparire un boss subito dopo l'altro
+                self.next_boss_score = (self.game_score / BOSS_SCORE_INTERVAL + 1) * BOSS_SCORE_INTERVAL;
+            }
+            reached
+        };
+
+        if milestone_reached{
+            self.spawn_boss();
+        }
+    }
+
+    fn spawn_boss(&mut self){
+        // compare a distanza dal player in una direzione casuale, ogni boss ha piu' vita del precedente
+        let angle = rand::thread_rng().gen_range(0.0..360.0);
+        let position = self.utils.clamp_to_level(self.player.player_entity.get_position() + Utils::rotate_point(FPoint::new(250.0, 0.0), angle),
+            LEVEL_SPAWN_MARGIN);
+        let health = 400 + 200 * self.bosses_defeated as i32;
+
+        let mut boss = Boss::new(format!("boss_{}", self.bosses_defeated).as_str(), health, 30.0, position);
+        boss.score_value = 500 * (self.bosses_defeated as i32 + 1);
+        self.boss = Some(boss);
+
+        // lo zoom scelto dal player con la rotella viene ripristinato alla morte del boss
+        self.zoom_before_boss = self.main_camera.get_target_zoom();
+        self.main_camera.set_zoom(BOSS_ZOOM);
+        self.main_camera.add_trauma(0.4);
+    }
+
+    fn on_boss_death(&mut self, boss:&Boss){
+        let death = boss.get_death_info();
+        if death.is_player_kill(){
+            self.game_score += self.combo.register_kill(boss.score_value);
+        }
+        self.bosses_defeated += 1;
+        // i punti fatti durante lo scontro (boss compreso) non devono far partire subito il boss successivo
+        self.next_boss_score = self.next_boss_score.max((self.game_score / BOSS_SCORE_INTERVAL + 1) * BOSS_SCORE_INTERVAL);
+
+        for _ in 0..3{
+            self.particles.emit(&ParticleEmitterConfig::explosion(), death.position, FPoint::new(1.0, 0.0));
+        }
+        self.particles.emit(&ParticleEmitterConfig::blood(), death.position, death.hit_direction);
+        self.decals.add_enemy_death(&death);
+        self.audio.play_at("death", death.position);
+        self.main_camera.add_trauma(0.8);
+        self.hit_stop.trigger(0.15, 0.1);
+        self.main_camera.set_zoom(self.zoom_before_boss);
+
+        // ricompense garantite
+        for (kind, offset) in [(PickupKind::Health(30), FPoint::new(-15.0, 0.0)), (PickupKind::Currency(20), FPoint::new(15.0, 0.0)),
+            (PickupKind::Ammo(15), FPoint::new(0.0, 15.0))]{
+            self.gameobjects.push(Box::new(Pickup::new(kind, death.position + offset)));
+        }
+    }
+
+    // salva il record una sola volta a fine partita
+    fn record_high_score(&mut self){
+        if self.run_recorded{
+            return;
+        }
+        self.run_recorded = true;
+
+        let wave = if self.settings.wave_mode { Some(self.waves.get_wave()) } else { None };
+        self.new_record = self.high_score.record(self.game_score, wave);
+        if self.new_record
+            && let Err(error) = self.high_score.save(HIGH_SCORE_PATH){
+            println!("Errore salvataggio record: {}", error);
+        }
+    }
+
+    // eseguito per ogni nemico morto, dopo che e' gia' stato tolto dalla lista dei gameobjects
+    fn on_enemy_death(&mut self, death:&EnemyDeath){
+        // solo le uccisioni del player danno punti e drop, i nemici che si schiantano contro il player no
+        if death.is_player_kill(){
+            self.game_score += self.combo.register_kill(death.archetype.get_score_value());
+            self.spawn_drops(death);
+        }
+
+        self.particles.emit(&ParticleEmitterConfig::blood(), death.position, death.hit_direction);
+        self.particles.emit(&ParticleEmitterConfig::explosion(), death.position, FPoint::new(1.0, 0.0));
+        self.decals.add_enemy_death(death);
+        self.audio.play_at("death", death.position);
+        self.main_camera.add_trauma(0.2);
+        self.hit_stop.trigger(0.03, 0.2);
+    }
+
+    // oggetti lasciati a terra dal nemico, tirati dalla tabella dei drop del suo archetipo
+    fn spawn_drops(&mut self, death:&EnemyDeath){
+        let mut rng = rand::thread_rng();
+        for kind in death.archetype.get_drop_table().roll(&mut rng){
+            // piccolo sparpagliamento in modo che piu' drop non si sovrappongano
+            let offset = FPoint::new(rng.gen_range(-12.0..12.0), rng.gen_range(-12.0..12.0));
+            self.gameobjects.push(Box::new(Pickup::new(kind, death.position + offset)));
+        }
     }
 }
\ No newline at end of file
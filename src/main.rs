@@ -6,10 +6,22 @@ const SCREEN_HEIGHT:u32 = 600;
 
 mod game;
 mod modules;
-use game::Game;
+use game::{Game, GameConfig, SETTINGS_PATH};
+use modules::{AudioManager, GameSettings, VideoSettings};
+
+// conta le allocazioni per l'overlay di debug e per il benchmark dei pool, solo con --features alloc-counter
+#[cfg(feature = "alloc-counter")]
+#[global_allocator]
+static ALLOCATOR:modules::CountingAllocator = modules::CountingAllocator;
 
 fn main() -> Result<(), String>{
 
+    // benchmark delle allocazioni con e senza pool, senza aprire la finestra (cargo run --release --features alloc-counter -- --bench-pools)
+    if std::env::args().any(|arg| arg == "--bench-pools"){
+        modules::run_pool_benchmark(600, 4);
+        return Ok(());
+    }
+
     let screen_widht = SCREEN_WIDTH;
     let screen_height = SCREEN_HEIGHT;
     
@@ -17,17 +29,44 @@ fn main() -> Result<(), String>{
     let video_subsystem = sdl_context.video()?;
     let window = video_subsystem.window("Rust game", screen_widht, screen_height)
     .position_centered()
+    .resizable()
     .build()
     .unwrap();
 
     let mut canvas = window.into_canvas().build().expect("Errore creazione canvas");
-    let mut texture_creator = canvas.texture_creator();
+    let texture_creator = canvas.texture_creator();
     let mut event_pump = sdl_context.event_pump()?;
+    // ttf_context creato una sola volta, deve vivere quanto Game (i font caricati dipendono da esso)
+    let ttf_context = sdl2::ttf::init().map_err(|e| {e.to_string()})?;
 
     let mut last_frame:Instant = Instant::now();
     let mut delta_time:f32;
 
-    let mut game = Game::new(&mut canvas, &mut texture_creator, &mut event_pump).unwrap();
+    // si disegna sempre a 800x600 logici, SDL scala sulla finestra con letterboxing
+    let video_settings = VideoSettings::new(Some((screen_widht, screen_height)));
+
+    // audio: se non c'e' un dispositivo audio il gioco parte comunque (AudioManager disattivato)
+    let audio_manager = AudioManager::new(&sdl_context);
+
+    // gamepad opzionale: senza sottosistema si gioca solo con mouse e tastiera
+    let gamepad_subsystem = sdl_context.game_controller().map_err(|error| {
+        println!("Gamepad non disponibili: {}", error);
+    }).ok();
+
+    // impostazioni salvate dal menu di pausa, se il file non c'e' si usano quelle di default
+    let mut settings = GameSettings::load(SETTINGS_PATH).unwrap_or_else(|error| {
+        println!("Impostazioni di default ({}: {})", SETTINGS_PATH, error);
+        GameSettings::new()
+    });
+    // modalita' ondate con l'argomento --waves (es. cargo run -- --waves)
+    settings.wave_mode = std::env::args().any(|arg| arg == "--waves");
+
+    let config = GameConfig{
+        video_settings,
+        settings,
+        audio:audio_manager,
+    };
+    let mut game = Game::new(&mut canvas, &texture_creator, &ttf_context, &mut event_pump, config, gamepad_subsystem).unwrap();
     game.start()?;
 
     'running: loop{
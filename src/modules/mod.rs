@@ -1,9 +1,18 @@
 use core::error;
-use std::{any::Any, collections::HashMap, vec};
-
-use sdl2::{event::Event, image::LoadTexture, mouse::MouseButton, pixels::Color, rect::{FPoint, FRect, Point, Rect}, render::{Texture, TextureCreator, WindowCanvas}, surface::Surface, video::WindowContext};
+use std::{any::Any, collections::{HashMap, HashSet, VecDeque}, path::Path, vec};
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "alloc-counter")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "alloc-counter")]
+use std::sync::atomic::AtomicUsize;
+use std::fmt::Write as FmtWrite;
+
+use sdl2::{event::Event, image::LoadTexture, mouse::MouseButton, pixels::{Color, PixelFormatEnum}, rect::{FPoint, FRect, Point, Rect}, render::{BlendMode, Texture, TextureCreator, WindowCanvas}, surface::Surface, video::{FullscreenType, WindowContext}};
 use sdl2::keyboard::Keycode;
-use sdl2::ttf::Font;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::mixer::{Channel, Chunk, InitFlag, Music, Sdl2MixerContext, MAX_VOLUME};
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::{AudioSubsystem, EventPump, GameControllerSubsystem, Sdl};
 use rand::Rng;
 use crate::game::{self, Game};
 // ------------- DEFINIZIONE TRATTI --------------
@@ -17,15 +26,46 @@ pub trait GameObject : Any{ // gameObject sotto tratto di Any
 }
 
 pub trait Damageable : GameObject { // tratto che devono implementare tutti gli oggetti che prendono danno, sottotratto, quindi se si implementa Damageable bisogna implementare anche GameObject
-    fn take_damage(&mut self, damage:i32);
+    // applica il danno passando per resistenze e pipeline, restituisce il danno effettivamente inflitto
+    fn take_damage(&mut self, damage:&DamageInfo) -> DamageInfo;
     fn get_current_health(&self) -> i32;
+    fn get_max_health(&self) -> i32;
     fn get_entity(&self) -> &Entity;
+
+    // finestra in cui i danni vengono ignorati (es. dopo essere stati colpiti, durante il dash)
+    fn is_invulnerable(&self) -> bool{
+        false
+    }
+
+    // spinta ricevuta quando si viene colpiti, direction normalizzata. Di base nessun effetto
+    fn apply_knockback(&mut self, _direction:FPoint, _strength:f32){
+    }
+
+    // resistenze intrinseche dell'oggetto (passate alla DamagePipeline in take_damage), di base nessuna
+    fn get_resistances(&self) -> Resistances{
+        Resistances::none()
+    }
+
+    // percentuale di vita rimasta tra 0.0 e 1.0 (usata dalle barre della vita)
+    fn get_health_fraction(&self) -> f32{
+        if self.get_max_health() > 0{
+            (self.get_current_health() as f32 / self.get_max_health() as f32).clamp(0.0, 1.0)
+        }else{
+            0.0
+        }
+    }
+}
+
+// modificatore del danno in arrivo (scudi, armature, buff...), eseguito dalla DamagePipeline prima di togliere la vita.
+// Puo' cambiare amount (anche a 0 per assorbire tutto il colpo) e tenere il proprio stato (es. punti scudo rimasti)
+pub trait DamageModifier{
+    fn modify(&mut self, damage:&mut DamageInfo);
 }
 
 // ------------- DEFINIZIONE STRUCTS ed ENUMS -------------
 
 // si implementa tratto copy per evitare di dover mettere il lifetime in bullet quando si passa EntityType
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum EntityType {
     Player,
     Enemy,
@@ -42,11 +82,15 @@ pub enum PlayerState{
     Reload=3,
 }
 
+pub const LEVEL_SPAWN_MARGIN:f32 = 70.0; // distanza minima dal bordo del livello per gli spawn (supera lo spessore dei muri esterni)
+
 pub struct EnemySpawner{
     // min e max in quanto nemici vengono spawnati con statistiche casuali in base a cio'
     pub spawn_rate:(f32, f32), // min e max
     pub health_enemies:(i32, i32), // min e max
     pub speed_enemies:(f32, f32), // min e max
+    pub enemies_per_spawn:(u32, u32), // min e max (inclusi)
+    pub archetype_weights:[f32; 3], // probabilita' relativa di ogni archetipo, stesso ordine di EnemyArchetype::ALL
     enemy_id:u32,
 
     current_spawn_rate:f32,
@@ -59,6 +103,8 @@ impl EnemySpawner{
             current_spawn_rate:enemy_spawn_rate.0,
             health_enemies:health_enemies_range,
             speed_enemies:speed_enemies_range,
+            enemies_per_spawn:(2, 2),
+            archetype_weights:[1.0, 1.0, 1.0],
             enemy_id:0, // inizialemente a 0 -> ad ogni nemico che viene spawnato si incrementa di 1
         }
     }
@@ -70,25 +116,20 @@ impl EnemySpawner{
             let enemy_health = rand::thread_rng().gen_range(self.health_enemies.0..self.health_enemies.1);
             let enemy_speed = rand::thread_rng().gen_range(self.speed_enemies.0..self.speed_enemies.1);
 
-            let enemies_to_spawn = 2; // numero di nemici da spawnare in contemporanea (per ora fisso)
+            let enemies_to_spawn = rand::thread_rng().gen_range(self.enemies_per_spawn.0..=self.enemies_per_spawn.1.max(self.enemies_per_spawn.0)); // numero di nemici da spawnare in contemporanea
             let mut enemies_spawned:Vec<Enemy> = Vec::new(); // vettore nel quale metterli
             // ognuno spawna con posizione casuale, per ora stessa velocita' e vita
             for _ in 0..enemies_to_spawn{
-                let mut new_enemy = Enemy::new(format!("enemy_base_{}", self.enemy_id).as_str(), enemy_speed, enemy_health);
+                // archetipo casuale (secondo i pesi), modifica vita, velocita' e resistenze
+                let archetype = self.pick_archetype();
+                let mut new_enemy = Enemy::with_archetype(format!("enemy_{}_{}", archetype.get_name(), self.enemy_id).as_str(),
+                    archetype, enemy_speed, enemy_health);
                 self.enemy_id += 1; // si incrementa enemy_id
     
                 // si imposta sprite
                 new_enemy.enemy_entity.set_sprite(51, 43);
-                // si imposta posizione (casualizzarla)
-                let offset_player_x_min = game_utils.get_player_position().x - 100.0;
-                let offset_player_x_max = game_utils.get_player_position().x + 100.0;
-                let offset_player_y_min = game_utils.get_player_position().y - 100.0;
-                let offset_player_y_max = game_utils.get_player_position().y + 100.0;
-                
-                let spawn_position_x = rand::thread_rng().gen_range(offset_player_x_min..offset_player_x_max);
-                let spawn_position_y = rand::thread_rng().gen_range(offset_player_y_min..offset_player_y_max);
-                new_enemy.enemy_entity.set_position(FPoint::new(spawn_position_x, 
-                    spawn_position_y));
+                // si imposta posizione casuale vicino al player
+                new_enemy.enemy_entity.set_position(EnemySpawner::spawn_position(game_utils));
                 
                 // spawn rate casuale tra minimo e massimo
                 self.current_spawn_rate = rand::thread_rng().gen_range(self.spawn_rate.0..self.spawn_rate.1);
@@ -103,6 +144,30 @@ impl EnemySpawner{
         }
     }
 
+    // nemico di un archetipo specifico, con statistiche casuali nei range attuali (usato dalle ondate)
+    pub fn spawn_archetype(&mut self, archetype:EnemyArchetype, health_multiplier:f32, game_utils:&Utils) -> Enemy{
+        let enemy_health = rand::thread_rng().gen_range(self.health_enemies.0..self.health_enemies.1);
+        let enemy_speed = rand::thread_rng().gen_range(self.speed_enemies.0..self.speed_enemies.1);
+        let enemy_health = ((enemy_health as f32 * health_multiplier) as i32).max(1);
+
+        let mut new_enemy = Enemy::with_archetype(format!("enemy_{}_{}", archetype.get_name(), self.enemy_id).as_str(),
+            archetype, enemy_speed, enemy_health);
+        self.enemy_id += 1;
+
+        new_enemy.enemy_entity.set_sprite(51, 43);
+        new_enemy.enemy_entity.set_position(EnemySpawner::spawn_position(game_utils));
+
+        new_enemy
+    }
+
+    // posizione casuale entro 100 pixel dal player, riportata dentro i muri esterni del livello
+    fn spawn_position(game_utils:&Utils) -> FPoint{
+        let player_position = game_utils.get_player_position();
+        game_utils.clamp_to_level(FPoint::new(
+            rand::thread_rng().gen_range(player_position.x - 100.0..player_position.x + 100.0),
+            rand::thread_rng().gen_range(player_position.y - 100.0..player_position.y + 100.0)), LEVEL_SPAWN_MARGIN)
+    }
+
     pub fn spawn_enemy(&mut self, deltatime:f32, game_utils:&Utils) -> Option<Enemy>{ // movimento dell'enemy
         if self.current_spawn_rate <= 0.0{
 
@@ -115,16 +180,8 @@ impl EnemySpawner{
 
             // si imposta sprite
             new_enemy.enemy_entity.set_sprite(51, 43);
-            // si imposta posizione (casualizzarla)
-            let offset_player_x_min = game_utils.get_player_position().x - 100.0;
-            let offset_player_x_max = game_utils.get_player_position().x + 100.0;
-            let offset_player_y_min = game_utils.get_player_position().y - 100.0;
-            let offset_player_y_max = game_utils.get_player_position().y + 100.0;
-            
-            let spawn_position_x = rand::thread_rng().gen_range(offset_player_x_min..offset_player_x_max);
-            let spawn_position_y = rand::thread_rng().gen_range(offset_player_y_min..offset_player_y_max);
-            new_enemy.enemy_entity.set_position(FPoint::new(spawn_position_x, 
-                spawn_position_y));
+            // si imposta posizione casuale vicino al player
+            new_enemy.enemy_entity.set_position(EnemySpawner::spawn_position(game_utils));
             
             // spawn rate casuale tra minimo e massimo
             self.current_spawn_rate = rand::thread_rng().gen_range(self.spawn_rate.0..self.spawn_rate.1);
@@ -135,17 +192,114 @@ impl EnemySpawner{
         }
     }
 
-    pub fn increase_difficulty(&mut self){
-        if self.spawn_rate.0 > 0.5 {
-            self.spawn_rate = (self.spawn_rate.0 - 0.25, self.spawn_rate.1 - 0.25);
+    // estrazione pesata con archetype_weights, se i pesi sono tutti a 0 si usa il Grunt
+    fn pick_archetype(&self) -> EnemyArchetype{
+        let total:f32 = self.archetype_weights.iter().map(|weight| weight.max(0.0)).sum();
+        if total <= 0.0{
+            return EnemyArchetype::Grunt;
+        }
+
+        let mut roll = rand::thread_rng().gen_range(0.0..total);
+        for (archetype, weight) in EnemyArchetype::ALL.iter().zip(self.archetype_weights.iter()){
+            if roll < weight.max(0.0){
+                return *archetype;
+            }
+            roll -= weight.max(0.0);
+        }
+        EnemyArchetype::Grunt
+    }
+}
+
+// la barra della vita sopra il nemico rimane visibile per ENEMY_HEALTH_BAR_VISIBLE_TIME secondi dall'ultimo danno,
+// poi sparisce gradualmente in ENEMY_HEALTH_BAR_FADE_TIME secondi
+const ENEMY_HEALTH_BAR_VISIBLE_TIME:f32 = 2.0;
+const ENEMY_HEALTH_BAR_FADE_TIME:f32 = 1.0;
+const ENEMY_HEALTH_BAR_SIZE:(u32, u32) = (30, 4);
+const ENEMY_COLLISION_RADIUS:f32 = 14.0;
+
+// tipi di nemico: cambiano statistiche di base e resistenze
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum EnemyArchetype{
+    Grunt, // nemico base
+    Runner, // veloce e fragile, debole agli esplosivi
+    Brute, // lento e resistente ai proiettili, debole al fuoco
+}
+
+impl EnemyArchetype{
+    pub const ALL:[EnemyArchetype; 3] = [EnemyArchetype::Grunt, EnemyArchetype::Runner, EnemyArchetype::Brute];
+
+    pub fn get_name(&self) -> &'static str{
+        match self{
+            EnemyArchetype::Grunt => "grunt",
+            EnemyArchetype::Runner => "runner",
+            EnemyArchetype::Brute => "brute",
+        }
+    }
+
+    // moltiplicatori (vita, velocita') applicati alle statistiche casuali dello spawner
+    pub fn get_stat_multipliers(&self) -> (f32, f32){
+        match self{
+            EnemyArchetype::Grunt => (1.0, 1.0),
+            EnemyArchetype::Runner => (0.6, 1.6),
+            EnemyArchetype::Brute => (2.5, 0.6),
+        }
+    }
+
+    pub fn get_resistances(&self) -> Resistances{
+        match self{
+            EnemyArchetype::Grunt => Resistances::none(),
+            EnemyArchetype::Runner => Resistances::new(0.0, -0.5, 0.0),
+            EnemyArchetype::Brute => Resistances::new(0.4, 0.2, -0.5),
+        }
+    }
+
+    // ogni voce della tabella viene tirata separatamente, quindi un nemico puo' lasciare piu' oggetti
+    pub fn get_drop_table(&self) -> DropTable{
+        match self{
+            EnemyArchetype::Grunt => DropTable::new(vec![
+                (PickupKind::Currency(1), 0.5),
+                (PickupKind::Health(10), 0.08),
+                (PickupKind::Ammo(5), 0.08),
+                (PickupKind::BulletPower(BulletPower::Ricochet, 8.0), 0.03),
+            ]),
+            EnemyArchetype::Runner => DropTable::new(vec![
+                (PickupKind::Currency(1), 0.3),
+                (PickupKind::SpeedBoost(5.0), 0.1),
+                (PickupKind::BulletPower(BulletPower::Homing, 8.0), 0.05),
+            ]),
+            EnemyArchetype::Brute => DropTable::new(vec![
+                (PickupKind::Currency(5), 0.9),
+                (PickupKind::Health(25), 0.25),
+                (PickupKind::Ammo(10), 0.2),
+                (PickupKind::RapidFire(6.0), 0.1),
+                (PickupKind::BulletPower(BulletPower::Pierce, 10.0), 0.1),
+            ]),
+        }
+    }
+
+    // punti base per un'uccisione, poi moltiplicati dalla combo
+    pub fn get_score_value(&self) -> i32{
+        match self{
+            EnemyArchetype::Grunt => 10,
+            EnemyArchetype::Runner => 15,
+            EnemyArchetype::Brute => 30,
         }
+    }
 
-        if self.health_enemies.0 < 40 {
-            self.health_enemies = (self.health_enemies.0 + 5, self.health_enemies.1 + 5);
+    pub fn get_contact_damage(&self) -> i32{
+        match self{
+            EnemyArchetype::Grunt => 10,
+            EnemyArchetype::Runner => 5,
+            EnemyArchetype::Brute => 20,
         }
+    }
 
-        if self.speed_enemies.0 < 70.0 {
-            self.speed_enemies = (self.speed_enemies.0 + 5.0, self.speed_enemies.1 + 5.0);
+    // danno fisso tolto a ogni colpo (ArmorModifier), 0 = nessuna armatura
+    pub fn get_armor(&self) -> i32{
+        match self{
+            EnemyArchetype::Grunt => 0,
+            EnemyArchetype::Runner => 0,
+            EnemyArchetype::Brute => 2,
         }
     }
 }
@@ -155,23 +309,104 @@ pub struct Enemy{
     health:i32,
     current_health:i32,
     speed:f32,
+    time_since_damage:Option<f32>, // None se il nemico non e' mai stato colpito (barra nascosta)
+    pub contact_damage:i32, // danno inflitto al player al contatto
+    pub knockback_strength:f32, // velocita' iniziale (pixel al secondo) della spinta data al player
+    pub archetype:EnemyArchetype,
+    pub resistances:Resistances,
+    pub damage_pipeline:DamagePipeline,
+    last_hit_direction:FPoint, // direzione dell'ultimo colpo ricevuto, usata per orientare corpo e sangue alla morte
+    killed_by:Option<EntityType>, // chi ha inflitto il colpo mortale, None se il nemico si e' distrutto da solo (contatto)
+}
+
+// tutto quello che serve per gli effetti di morte, raccolto prima che il nemico venga tolto dalla lista dei gameobjects
+pub struct EnemyDeath{
+    pub position:FPoint,
+    pub rotation:f64,
+    pub hit_direction:FPoint,
+    pub archetype:EnemyArchetype,
+    pub sprite:Rect, // frame dello spritesheet con cui era disegnato il nemico
+    pub killed_by:Option<EntityType>,
+}
+
+impl EnemyDeath{
+    // ucciso dal player (non morto contro di lui)
+    pub fn is_player_kill(&self) -> bool{
+        self.killed_by == Some(EntityType::Player)
+    }
 }
 
 impl Enemy{
     pub fn new(name:&str, speed:f32, health:i32) -> Enemy{
+        Enemy::with_archetype(name, EnemyArchetype::Grunt, speed, health)
+    }
+
+    // speed e health sono quelli di base, vengono scalati in base all'archetipo
+    pub fn with_archetype(name:&str, archetype:EnemyArchetype, speed:f32, health:i32) -> Enemy{
+        let (health_multiplier, speed_multiplier) = archetype.get_stat_multipliers();
+        let health = ((health as f32 * health_multiplier) as i32).max(1);
+        let speed = speed * speed_multiplier;
+
+        let mut damage_pipeline = DamagePipeline::new();
+        if archetype.get_armor() > 0{
+            damage_pipeline.add_modifier(Box::new(ArmorModifier::new(archetype.get_armor())));
+        }
+
         Enemy{
             enemy_entity: Entity::with_speed(name, speed, EntityType::Enemy),
             health:health,
             current_health:health,
             speed:speed,
+            time_since_damage:None,
+            contact_damage:archetype.get_contact_damage(),
+            knockback_strength:350.0,
+            archetype,
+            resistances:archetype.get_resistances(),
+            damage_pipeline,
+            last_hit_direction:FPoint::new(0.0, 0.0),
+            killed_by:None,
+        }
+    }
+
+    pub fn get_death_info(&self) -> EnemyDeath{
+        EnemyDeath{
+            position:self.enemy_entity.get_position(),
+            rotation:self.enemy_entity.get_rotation(),
+            hit_direction:self.last_hit_direction,
+            archetype:self.archetype,
+            sprite:self.enemy_entity.entity_sprite.sprite,
+            killed_by:self.killed_by,
+        }
+    }
+
+    // opacita' della barra della vita in base al tempo passato dall'ultimo danno
+    fn health_bar_alpha(&self) -> u8{
+        match self.time_since_damage{
+            Some(time) if time < ENEMY_HEALTH_BAR_VISIBLE_TIME => 255,
+            Some(time) if time < ENEMY_HEALTH_BAR_VISIBLE_TIME + ENEMY_HEALTH_BAR_FADE_TIME => {
+                let fade = 1.0 - (time - ENEMY_HEALTH_BAR_VISIBLE_TIME) / ENEMY_HEALTH_BAR_FADE_TIME;
+                (fade * 255.0) as u8
+            },
+            _ => 0,
         }
     }
 
     // funzione eseguita in game.update() per ogni nemico
-    pub fn damage_player(&mut self, player:&mut Player){
-        if Utils::calculate_point_distance(self.enemy_entity.position, player.player_entity.get_position()) < 15.0{
-            player.take_damage(10); //damage fisso
-            println!("Vita player : {}", player.current_health);
+    // danno da contatto: passa per il tratto Damageable, quindi rispetta invulnerabilita' e knockback del bersaglio.
+    // Se il bersaglio e' invulnerabile il nemico non lo colpisce (e non si distrugge), quindi piu' nemici
+    // che arrivano nello stesso frame colpiscono una volta sola
+    pub fn damage_player<T>(&mut self, player:&mut T) where T : Damageable{
+        if player.is_invulnerable(){
+            return;
+        }
+
+        let player_position = player.get_entity().get_position();
+        if Utils::calculate_point_distance(self.enemy_entity.position, player_position) < 15.0{
+            let direction = Utils::point_normalized(player_position - self.enemy_entity.position);
+            player.take_damage(&DamageInfo::new(self.contact_damage, DamageType::Kinetic, EntityType::Enemy).with_direction(direction)
+                .with_source_id(Some(self.enemy_entity.get_id())));
+            player.apply_knockback(direction, self.knockback_strength);
+            println!("Vita player : {}", player.get_current_health());
             self.current_health = -1; // imposto vita a -1 in modo che poi si autodistrugga
         }
     }
@@ -187,7 +422,18 @@ impl GameObject for Enemy{
     }
 
     fn draw(&mut self, canvas:&mut WindowCanvas, texture:&Texture, animation_frame:u32, game_utils:&Utils, scale_factor:f32) -> Result<(), String> {
-        self.enemy_entity.draw(canvas, texture, animation_frame, game_utils, scale_factor)
+        self.enemy_entity.draw(canvas, texture, animation_frame, game_utils, scale_factor)?;
+
+        // barra della vita in world space, sopra lo sprite, solo se il nemico e' stato colpito di recente
+        let alpha = self.health_bar_alpha();
+        if alpha > 0{
+            let sprite_height = self.enemy_entity.entity_sprite.sprite.height() as f32 * scale_factor;
+            let bar_center = self.enemy_entity.get_position() - FPoint::new(0.0, sprite_height / 2.0 + 6.0);
+            Utils::draw_world_bar(canvas, game_utils, bar_center, ENEMY_HEALTH_BAR_SIZE, self.get_health_fraction(),
+                Color::RGBA(200, 30, 30, alpha), Color::RGBA(40, 40, 40, alpha))?;
+        }
+
+        Ok(())
     }
 
     fn get_name(&self) -> &str {
@@ -208,7 +454,11 @@ impl GameObject for Enemy{
         // si controlla vicinanza con player
         if Utils::calculate_point_distance(self.enemy_entity.position, 
             game_utils.get_player_position()) > 15.0{ // se player e' massimo lontano 15 pixel
-            self.enemy_entity.update(deltatime, game_utils); // update di base dell'entity per movimento sulla base di movement_direction
+            self.enemy_entity.update_with_walls(deltatime, game_utils, ENEMY_COLLISION_RADIUS); // movimento sulla base di movement_direction, bloccato dai muri
+        }
+
+        if let Some(time) = self.time_since_damage.as_mut(){
+            *time += deltatime;
         }
     }
 
@@ -226,8 +476,24 @@ impl Damageable for Enemy{
         self.current_health
     }
 
-    fn take_damage(&mut self, damage:i32) {
-        self.current_health -= damage;
+    fn get_max_health(&self) -> i32 {
+        self.health
+    }
+
+    fn take_damage(&mut self, damage:&DamageInfo) -> DamageInfo {
+        let resistances = self.get_resistances();
+        let resolved = self.damage_pipeline.resolve(damage, &resistances);
+        self.current_health -= resolved.amount;
+        self.time_since_damage = Some(0.0); // si mostra la barra della vita
+        self.last_hit_direction = resolved.direction;
+        if self.current_health <= 0 && self.killed_by.is_none(){
+            self.killed_by = Some(resolved.source);
+        }
+        resolved
+    }
+
+    fn get_resistances(&self) -> Resistances {
+        self.resistances
     }
 
     fn get_entity(&self) -> &Entity {
@@ -235,13 +501,145 @@ impl Damageable for Enemy{
     }
 }
 
+// comportamenti aggiuntivi di un proiettile, combinabili tra loro (armi e potenziamenti)
+#[derive(Clone, Copy, Debug)]
+pub struct BulletModifiers{
+    pub pierce:u32, // nemici che il proiettile puo' attraversare prima di fermarsi
+    pub ricochets:u32, // rimbalzi sui muri
+    pub homing_turn_rate:f32, // gradi al secondo con cui curva verso il bersaglio piu' vicino, 0.0 -> niente ricerca
+    pub homing_range:f32,
+    pub explosion:Option<(f32, i32)>, // (raggio, danno) dell'esplosione quando il proiettile si ferma o scade
+}
+
+impl BulletModifiers{
+    pub fn none() -> Self{
+        BulletModifiers{
+            pierce:0,
+            ricochets:0,
+            homing_turn_rate:0.0,
+            homing_range:0.0,
+            explosion:None,
+        }
+    }
+
+    pub fn with_pierce(mut self, targets:u32) -> Self{
+        self.pierce += targets;
+        self
+    }
+
+    pub fn with_ricochets(mut self, ricochets:u32) -> Self{
+        self.ricochets += ricochets;
+        self
+    }
+
+    pub fn with_homing(mut self, turn_rate:f32, range:f32) -> Self{
+        self.homing_turn_rate = turn_rate;
+        self.homing_range = range;
+        self
+    }
+
+    pub fn with_explosion(mut self, radius:f32, damage:i32) -> Self{
+        self.explosion = Some((radius, damage));
+        self
+    }
+}
+
+// potenziamenti dei proiettili dati dai pickup, si sommano ai modificatori dell'arma finche' durano
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BulletPower{
+    Pierce,
+    Ricochet,
+    Homing,
+}
+
+impl BulletPower{
+    pub fn get_name(&self) -> &'static str{
+        match self{
+            BulletPower::Pierce => "Pierce",
+            BulletPower::Ricochet => "Ricochet",
+            BulletPower::Homing => "Homing",
+        }
+    }
+
+    pub fn apply(&self, modifiers:BulletModifiers) -> BulletModifiers{
+        match self{
+            BulletPower::Pierce => modifiers.with_pierce(2),
+            BulletPower::Ricochet => modifiers.with_ricochets(2),
+            BulletPower::Homing => modifiers.with_homing(180.0, 150.0),
+        }
+    }
+}
+
+// esplosione lasciata da un proiettile, applicata da Game a tutti i Damageable nel raggio
+#[derive(Clone, Copy, Debug)]
+pub struct BulletExplosion{
+    pub position:FPoint,
+    pub radius:f32,
+    pub damage:i32, // danno al centro, dimezzato sul bordo
+    pub owner:EntityType,
+    pub owner_id:Option<u32>,
+}
+
+// armi del player: cambiano i modificatori dei proiettili
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Weapon{
+    Pistol,
+    Piercer,
+    Ricochet,
+    Seeker,
+    Incendiary,
+}
+
+impl Weapon{
+    pub const ALL:[Weapon; 5] = [Weapon::Pistol, Weapon::Piercer, Weapon::Ricochet, Weapon::Seeker, Weapon::Incendiary];
+
+    pub fn get_name(&self) -> &'static str{
+        match self{
+            Weapon::Pistol => "Pistol",
+            Weapon::Piercer => "Piercer",
+            Weapon::Ricochet => "Ricochet",
+            Weapon::Seeker => "Seeker",
+            Weapon::Incendiary => "Incendiary",
+        }
+    }
+
+    pub fn get_modifiers(&self) -> BulletModifiers{
+        match self{
+            Weapon::Pistol => BulletModifiers::none(),
+            Weapon::Piercer => BulletModifiers::none().with_pierce(2),
+            Weapon::Ricochet => BulletModifiers::none().with_ricochets(3),
+            Weapon::Seeker => BulletModifiers::none().with_homing(180.0, 150.0),
+            Weapon::Incendiary => BulletModifiers::none(),
+        }
+    }
+
+    pub fn get_damage_type(&self) -> DamageType{
+        match self{
+            Weapon::Incendiary => DamageType::Fire,
+            _ => DamageType::Kinetic,
+        }
+    }
+
+    pub fn next(&self) -> Weapon{
+        let index = Weapon::ALL.iter().position(|weapon| weapon == self).unwrap_or(0);
+        Weapon::ALL[(index + 1) % Weapon::ALL.len()]
+    }
+}
+
 pub struct Bullet{
     bullet_entity:Entity, // entity relativa al bullet, contiene di base velocita', direzione, nome ecc..
     bullet_owner: EntityType, // assegnato alla creazione, per capire chi ha sparato il proiettile
+    owner_id:Option<u32>, // entity che ha sparato, passata come sorgente del danno
     bullet_life:i32, // vita del bullet in base alla distanza percorsa
     bullet_current_life:i32, // attuale distanza percorsa
     destroyed:bool, // si imposta a true quando ad esempio colpisce nemico o ostacolo
     bullet_damage:i32,
+    pub damage_type:DamageType,
+    pub crit_chance:f32, // probabilita' tra 0.0 e 1.0 di colpo critico
+    pub crit_multiplier:f32, // moltiplicatore del danno in caso di critico
+    pub modifiers:BulletModifiers,
+    hit_targets:Vec<u32>, // id delle entity gia' colpite, per non colpirle due volte attraversandole
+    explosion:Option<BulletExplosion>, // impostata quando il proiettile si ferma (se ha il modificatore)
 }
 
 impl Bullet{
@@ -249,21 +647,97 @@ impl Bullet{
         let mut new_bullet = Bullet{
             bullet_entity: Entity::with_speed("bullet", bullet_velocity, EntityType::Bullet),
             bullet_owner:bullet_owner,
-            bullet_life:200, // di base 200 pixel
+            owner_id:None,
+            bullet_life:0,
             bullet_current_life:0,
             destroyed:false,
-            bullet_damage:10, // per ora fisso
+            bullet_damage:0,
+            damage_type:DamageType::Kinetic,
+            crit_chance:0.0,
+            crit_multiplier:0.0,
+            modifiers:BulletModifiers::none(),
+            hit_targets:Vec::new(),
+            explosion:None,
         };
 
-        new_bullet.bullet_entity.change_direction(bullet_direction); // imposto la direzione al nuovo bullet creato
-        new_bullet.bullet_entity.set_position(bullet_starting_position); // posizione in cui istanziare il bullet
-
         new_bullet.bullet_entity.set_sprite(100,50); // imposto la dimensione dello sprite (coincide con dimensione missile.png per ora)
         // la texture poi si specifica quando si renderizza direttamente, quindi in Game.render()
 
+        new_bullet.reset(bullet_direction, bullet_owner, bullet_velocity, bullet_starting_position);
         new_bullet
     }
 
+    // riporta il bullet allo stato iniziale, usato sia alla creazione che quando viene riciclato dal pool.
+    // Entity, sprite e hit_targets vengono riutilizzati, quindi non si alloca nulla
+    pub fn reset(&mut self, bullet_direction:FPoint, bullet_owner:EntityType, bullet_velocity:f32, bullet_starting_position:FPoint){
+        self.bullet_owner = bullet_owner;
+        self.owner_id = None;
+        self.bullet_life = 200; // di base 200 pixel
+        self.bullet_current_life = 0;
+        self.destroyed = false;
+        self.bullet_damage = 10; // per ora fisso
+        self.damage_type = DamageType::Kinetic;
+        self.crit_chance = 0.1;
+        self.crit_multiplier = 2.0;
+        self.modifiers = BulletModifiers::none();
+        self.hit_targets.clear();
+        self.explosion = None;
+
+        self.bullet_entity.speed = bullet_velocity;
+        self.bullet_entity.change_direction(bullet_direction); // imposto la direzione al nuovo bullet creato
+        self.bullet_entity.set_position(bullet_starting_position); // posizione in cui istanziare il bullet
+        // rotazione subito corretta, altrimenti un bullet riciclato verrebbe disegnato per un frame con quella vecchia
+        self.bullet_entity.set_rotation((bullet_direction.y as f64).atan2(bullet_direction.x as f64).to_degrees());
+    }
+
+    pub fn get_explosion(&self) -> Option<&BulletExplosion>{
+        self.explosion.as_ref()
+    }
+
+    // il proiettile si ferma: se ha il modificatore esplode nel punto in cui si trova
+    fn stop(&mut self){
+        if self.destroyed{
+            return;
+        }
+        self.destroyed = true;
+
+        if let Some((radius, damage)) = self.modifiers.explosion{
+            self.explosion = Some(BulletExplosion{
+                position:self.bullet_entity.get_position(),
+                radius,
+                damage,
+                owner:self.bullet_owner,
+                owner_id:self.owner_id,
+            });
+        }
+    }
+
+    // curva verso il bersaglio piu' vicino nel raggio, al massimo di homing_turn_rate gradi al secondo
+    fn update_homing(&mut self, deltatime:f32, game_utils:&Utils){
+        let position = self.bullet_entity.get_position();
+        // i proiettili dei nemici cercano il player, quelli del player i nemici
+        let target = if self.bullet_owner == EntityType::Enemy{
+            Some(game_utils.get_player_position())
+        }else{
+            game_utils.enemy_positions.iter().copied()
+                .min_by(|a, b| Utils::calculate_point_distance(position, *a).total_cmp(&Utils::calculate_point_distance(position, *b)))
+        };
+
+        let target = match target{
+            Some(target) if Utils::calculate_point_distance(position, target) <= self.modifiers.homing_range => target,
+            _ => return,
+        };
+
+        let direction = self.bullet_entity.movement_direction;
+        let to_target = target - position;
+        let current_angle = (direction.y as f64).atan2(direction.x as f64).to_degrees();
+        let target_angle = (to_target.y as f64).atan2(to_target.x as f64).to_degrees();
+        // differenza riportata tra -180 e 180 per girare dal lato piu' corto
+        let difference = (target_angle - current_angle + 540.0) % 360.0 - 180.0;
+        let max_turn = (self.modifiers.homing_turn_rate * deltatime) as f64;
+        self.bullet_entity.change_direction(Utils::rotate_point(direction, difference.clamp(-max_turn, max_turn)));
+    }
+
     pub fn is_out_of_range(&self) -> bool{
         if self.bullet_current_life >= self.bullet_life{
             true
@@ -273,16 +747,37 @@ impl Bullet{
     }
 
     // metodo generico in modo da colpire qualsiasi entita' damageable SOLO SE il bullet owner e' diverso dall'entity type
-    pub fn damage_enemy<T>(&mut self, enemy:&mut T) where T : Damageable{ // metodo che viene eseguito nell'update di game.rs per ogni bullet
+    pub fn damage_enemy<T>(&mut self, enemy:&mut T, particles:&mut ParticleSystem, audio:&mut AudioManager, damage_numbers:&mut DamageNumbers) where T : Damageable{ // metodo che viene eseguito nell'update di game.rs per ogni bullet
+        if self.destroyed{
+            return;
+        }
+
         // se il bullet e' vicino al nemico i-esimo
         let bullet_range:f32 = 20.0; // vicinanza in pixel tra bullet e nemico per far si che il bullet possa colpirlo
-        if Utils::calculate_point_distance(self.bullet_entity.get_position(), enemy.get_entity().get_position()) < bullet_range{
-            if enemy.get_entity().entity_type != self.bullet_owner{
-                enemy.take_damage(self.bullet_damage); // damage fisso
-                println!("{} health : {}", enemy.get_entity().entity_name, enemy.get_current_health()); // stampo la vita rimanente
-
+        if Utils::calculate_point_distance(self.bullet_entity.get_position(), enemy.get_entity().get_position()) < bullet_range
+            && enemy.get_entity().entity_type != self.bullet_owner && !self.hit_targets.contains(&enemy.get_entity().get_id()){
+            let critical = rand::thread_rng().gen_bool(self.crit_chance.clamp(0.0, 1.0) as f64);
+            let amount = if critical { (self.bullet_damage as f32 * self.crit_multiplier) as i32 } else { self.bullet_damage };
+            let damage = DamageInfo::new(amount, self.damage_type, self.bullet_owner)
+                .with_direction(self.bullet_entity.movement_direction)
+                .with_critical(critical)
+                .with_source_id(self.owner_id);
+            let resolved = enemy.take_damage(&damage);
+            damage_numbers.spawn(&resolved, self.bullet_entity.get_position());
+
+            // scintille che rimbalzano indietro verso chi ha sparato e sangue nella direzione del proiettile
+            let bullet_direction = self.bullet_entity.movement_direction;
+            particles.emit(&ParticleEmitterConfig::impact(), self.bullet_entity.get_position(), bullet_direction * -1.0);
+            particles.emit(&ParticleEmitterConfig::blood(), enemy.get_entity().get_position(), bullet_direction);
+            audio.play_at("hit", enemy.get_entity().get_position());
+
+            // con pierce il proiettile continua, ricordando chi ha gia' colpito
+            if self.modifiers.pierce > 0{
+                self.modifiers.pierce -= 1;
+                self.hit_targets.push(enemy.get_entity().get_id());
+            }else{
                 self.bullet_damage = 0; // per evitare problemi (distrutto troppo tardi)
-                self.destroyed = true; // distruggo il bullet
+                self.stop(); // distruggo il bullet (ed eventualmente esplode)
             }
         }
     }
@@ -306,9 +801,37 @@ impl GameObject for Bullet{
     }
 
     fn update(&mut self, deltatime:f32, game_utils:&Utils) {
+        if self.destroyed{
+            return;
+        }
+
+        if self.modifiers.homing_turn_rate > 0.0{
+            self.update_homing(deltatime, game_utils);
+        }
+
+        let previous_position = self.bullet_entity.get_position();
         self.bullet_entity.update(deltatime, game_utils); // ho il movimento gia' gestito di base da Entity
 
         self.bullet_current_life += (self.bullet_entity.speed * deltatime) as i32; // aggiorno la distanza percorsa
+
+        let position = self.bullet_entity.get_position();
+        if game_utils.collides_with_walls(position, 2.0){
+            if self.modifiers.ricochets > 0{
+                // si riflette la componente del movimento che ha portato dentro al muro
+                self.modifiers.ricochets -= 1;
+                let direction = self.bullet_entity.movement_direction;
+                let hit_on_x = game_utils.collides_with_walls(FPoint::new(position.x, previous_position.y), 2.0);
+                let reflected = if hit_on_x { FPoint::new(-direction.x, direction.y) } else { FPoint::new(direction.x, -direction.y) };
+                self.bullet_entity.change_direction(reflected);
+                self.bullet_entity.set_position(previous_position);
+                // ogni rimbalzo colpisce di nuovo tutti
+                self.hit_targets.clear();
+            }else{
+                self.stop(); // il bullet si ferma sul muro
+            }
+        }else if self.is_out_of_range(){
+            self.stop(); // scaduto, esplode comunque se ha il modificatore
+        }
     }
 
     fn is_destroyed(&self) -> bool {
@@ -317,18 +840,168 @@ impl GameObject for Bullet{
 }
 
 pub struct Camera{
-    camera_position:FPoint,
+    camera_position:FPoint, // angolo in alto a sinistra della vista in world space
+    velocity:FPoint, // velocita' attuale della molla che segue il player
+    pub smooth_time:f32, // tempo (circa) per raggiungere il target, 0.0 -> la camera scatta subito sul target
+    pub dead_zone:(f32, f32), // meta' larghezza e meta' altezza della zona centrale in cui il player puo' muoversi senza spostare la camera
+    pub look_ahead:f32, // di quanti pixel la camera si sposta verso la direzione in cui punta il mouse
+    pub bounds:Option<FRect>, // limiti della mappa in world space, None -> nessun limite
+    started:bool, // al primo update la camera si posiziona direttamente sul player
+
+    // screen shake basato su "trauma": ogni evento aggiunge trauma (max 1.0) che decade nel tempo,
+    // lo spostamento e' proporzionale a trauma^2 in modo che i colpi leggeri si notino poco
+    trauma:f32,
+    shake_time:f32, // tempo che scorre per campionare il rumore
+    shake_offset:FPoint,
+    shake_angle:f64,
+    pub shake_enabled:bool, // impostazione di accessibilita'
+    pub max_shake_offset:f32, // pixel
+    pub max_shake_angle:f64, // gradi
+    pub trauma_decay:f32, // trauma perso al secondo
+
+    // zoom: > 1.0 avvicina, < 1.0 allontana. zoom tende a target_zoom con velocita' zoom_speed
+    zoom:f32,
+    target_zoom:f32,
+    pub zoom_speed:f32,
+    pub zoom_range:(f32, f32), // min e max
 }
 
 impl Camera{
     pub fn new() -> Self{
+        Camera::with_follow(0.15, (20.0, 15.0), 60.0)
+    }
+
+    pub fn with_follow(smooth_time:f32, dead_zone:(f32, f32), look_ahead:f32) -> Self{
         Camera{
             camera_position: FPoint::new(0.0, 0.0),
+            velocity: FPoint::new(0.0, 0.0),
+            smooth_time,
+            dead_zone,
+            look_ahead,
+            bounds:None,
+            started:false,
+            trauma:0.0,
+            shake_time:0.0,
+            shake_offset:FPoint::new(0.0, 0.0),
+            shake_angle:0.0,
+            shake_enabled:true,
+            max_shake_offset:12.0,
+            max_shake_angle:2.0,
+            trauma_decay:1.5,
+            zoom:1.0,
+            target_zoom:1.0,
+            zoom_speed:8.0,
+            zoom_range:(0.5, 2.0),
         }
     }
 
+    pub fn get_zoom(&self) -> f32{
+        self.zoom
+    }
+
+    // zoom verso cui la camera si sta muovendo (quello impostato con la rotella o da set_zoom)
+    pub fn get_target_zoom(&self) -> f32{
+        self.target_zoom
+    }
+
+    // zoom "scriptato" (es. allontanare la vista durante un boss)
+    pub fn set_zoom(&mut self, zoom:f32){
+        self.target_zoom = zoom.clamp(self.zoom_range.0, self.zoom_range.1);
+    }
+
+    // zoom con la rotella del mouse: ogni scatto moltiplica lo zoom di un fattore fisso
+    pub fn zoom_by_steps(&mut self, steps:i32){
+        self.set_zoom(self.target_zoom * 1.1_f32.powi(steps));
+    }
+
+    // posizione usata per il rendering, comprende lo screen shake
     pub fn get_main_camera_position(&self) -> FPoint{
-        self.camera_position
+        self.camera_position + self.shake_offset
+    }
+
+    pub fn get_main_camera_rotation(&self) -> f64{
+        self.shake_angle
+    }
+
+    // da chiamare quando succede qualcosa che deve "scuotere" lo schermo (es. player colpito, nemico ucciso)
+    pub fn add_trauma(&mut self, amount:f32){
+        if self.shake_enabled{
+            self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+        }
+    }
+
+    // aggiornato con il deltatime reale (non scalato dall'hit-stop) cosi' lo shake continua anche durante il freeze
+    pub fn update_shake(&mut self, deltatime:f32){
+        if !self.shake_enabled || self.trauma <= 0.0{
+            self.trauma = 0.0;
+            self.shake_offset = FPoint::new(0.0, 0.0);
+            self.shake_angle = 0.0;
+            return;
+        }
+
+        self.shake_time += deltatime;
+        let shake = self.trauma * self.trauma;
+        let frequency = 25.0; // velocita' di variazione del rumore
+
+        // tre canali di rumore indipendenti (seed diversi) per x, y e rotazione
+        self.shake_offset = FPoint::new(
+            self.max_shake_offset * shake * Camera::shake_noise(1.0, self.shake_time * frequency),
+            self.max_shake_offset * shake * Camera::shake_noise(7.0, self.shake_time * frequency),
+        );
+        self.shake_angle = self.max_shake_angle * shake as f64 * Camera::shake_noise(13.0, self.shake_time * frequency) as f64;
+
+        self.trauma = (self.trauma - self.trauma_decay * deltatime).max(0.0);
+    }
+
+    // rumore continuo in [-1, 1]: somma di sinusoidi con frequenze non multiple tra loro,
+    // abbastanza irregolare per lo shake ma senza gli scatti di un valore casuale per frame
+    fn shake_noise(seed:f32, time:f32) -> f32{
+        (time + seed).sin() * 0.5
+            + (time * 2.3 + seed * 1.7).sin() * 0.3
+            + (time * 4.7 + seed * 2.9).sin() * 0.2
+    }
+
+    pub fn set_bounds(&mut self, bounds:Option<FRect>){
+        self.bounds = bounds;
+    }
+
+    // molla con smorzamento critico (stessa approssimazione di SmoothDamp): arriva sul target senza oscillare
+    // e senza scatti anche se il target cambia ogni frame
+    fn smooth_damp(current:f32, target:f32, velocity:&mut f32, smooth_time:f32, deltatime:f32) -> f32{
+        if smooth_time <= 0.0{
+            *velocity = 0.0;
+            return target;
+        }
+
+        let omega = 2.0 / smooth_time;
+        let x = omega * deltatime;
+        let exp = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+        let change = current - target;
+        let temp = (*velocity + omega * change) * deltatime;
+        *velocity = (*velocity - omega * temp) * exp;
+
+        target + (change + temp) * exp
+    }
+
+    // sposta la coordinata del centro della camera solo se il focus esce dalla dead zone
+    fn apply_dead_zone(center:f32, focus:f32, dead_zone:f32) -> f32{
+        if focus - center > dead_zone{
+            focus - dead_zone
+        }else if focus - center < -dead_zone{
+            focus + dead_zone
+        }else{
+            center
+        }
+    }
+
+    // limita una coordinata del centro della vista in modo che la vista non esca dalla mappa.
+    // Se la mappa e' piu' piccola della vista la si centra
+    fn clamp_axis(center:f32, view_size:f32, bounds_start:f32, bounds_size:f32) -> f32{
+        if bounds_size <= view_size{
+            bounds_start + bounds_size / 2.0
+        }else{
+            center.clamp(bounds_start + view_size / 2.0, bounds_start + bounds_size - view_size / 2.0)
+        }
     }
 }
 
@@ -351,9 +1024,42 @@ impl GameObject for Camera{
     }
 
     fn update(&mut self, deltatime:f32, game_utils:&Utils) {
-        // camera segue il player
-        // 400, 300 rappresenta la metà della dimensione della finestra -> MODIFICARE PASSANDO IL CANVAS!!!
-        self.camera_position = game_utils.get_player_position() - FPoint::new(400.0, 300.0);
+        // camera segue il player, centrandolo nella dimensione attuale della vista (logica o della finestra)
+        let half_screen = FPoint::new(game_utils.screen_size.0 as f32 / 2.0, game_utils.screen_size.1 as f32 / 2.0);
+        let player_position = game_utils.get_player_position();
+
+        // look-ahead: il punto seguito si sposta dal player verso la direzione di mira (mouse o stick)
+        let aim_direction = game_utils.get_aim_direction(player_position);
+        let focus = player_position + aim_direction * self.look_ahead;
+
+        let current_center = self.camera_position + half_screen;
+        let new_center = if !self.started{
+            self.started = true;
+            focus // primo frame: nessuna interpolazione
+        }else{
+            let target = FPoint::new(
+                Camera::apply_dead_zone(current_center.x, focus.x, self.dead_zone.0),
+                Camera::apply_dead_zone(current_center.y, focus.y, self.dead_zone.1),
+            );
+            FPoint::new(
+                Camera::smooth_damp(current_center.x, target.x, &mut self.velocity.x, self.smooth_time, deltatime),
+                Camera::smooth_damp(current_center.y, target.y, &mut self.velocity.y, self.smooth_time, deltatime),
+            )
+        };
+
+        // zoom: si avvicina al target in modo esponenziale (indipendente dal frame rate)
+        self.zoom += (self.target_zoom - self.zoom) * (1.0 - (-self.zoom_speed * deltatime).exp());
+
+        // limiti della mappa: con lo zoom la vista copre screen_size / zoom in world space
+        let new_center = match self.bounds{
+            Some(bounds) => FPoint::new(
+                Camera::clamp_axis(new_center.x, game_utils.screen_size.0 as f32 / self.zoom, bounds.x, bounds.w),
+                Camera::clamp_axis(new_center.y, game_utils.screen_size.1 as f32 / self.zoom, bounds.y, bounds.h),
+            ),
+            None => new_center,
+        };
+
+        self.camera_position = new_center - half_screen;
     }
 
     fn is_destroyed(&self) -> bool {
@@ -361,10 +1067,134 @@ impl GameObject for Camera{
     }
 }
 
+// hit-stop: per un breve periodo il tempo di gioco viene rallentato (o fermato) per dare peso ai colpi
+pub struct HitStop{
+    remaining:f32, // secondi reali rimanenti
+    time_scale:f32, // scala del tempo durante l'hit-stop
+    pub enabled:bool, // impostazione di accessibilita'
+}
+
+impl HitStop{
+    pub fn new(enabled:bool) -> Self{
+        HitStop{
+            remaining:0.0,
+            time_scale:1.0,
+            enabled,
+        }
+    }
+
+    // se c'e' gia' un hit-stop attivo si tiene la durata maggiore e il rallentamento piu' forte
+    pub fn trigger(&mut self, duration:f32, time_scale:f32){
+        if !self.enabled{
+            return;
+        }
+
+        if self.remaining > 0.0{
+            self.time_scale = self.time_scale.min(time_scale);
+        }else{
+            self.time_scale = time_scale;
+        }
+        self.remaining = self.remaining.max(duration);
+    }
+
+    // deltatime reale, l'hit-stop non deve rallentare se stesso
+    pub fn update(&mut self, deltatime:f32){
+        if self.remaining > 0.0{
+            self.remaining -= deltatime;
+        }
+    }
+
+    pub fn get_time_scale(&self) -> f32{
+        if self.enabled && self.remaining > 0.0{
+            self.time_scale.clamp(0.0, 1.0)
+        }else{
+            1.0
+        }
+    }
+}
+
+// impostazioni di gioco modificabili dal giocatore
+pub struct GameSettings{
+    pub screen_shake:bool,
+    pub hit_stop:bool,
+    pub aim_toggle_required:bool, // se true si spara solo dopo essere entrati in modalita' mira (ToggleAim)
+    pub master_volume:f32, // volumi tra 0.0 e 1.0
+    pub sfx_volume:f32,
+    pub music_volume:f32,
+    pub wave_mode:bool, // ondate con intervalli al posto dello spawn continuo
+}
+
+impl GameSettings{
+    pub fn new() -> Self{
+        GameSettings{
+            screen_shake:true,
+            hit_stop:true,
+            aim_toggle_required:false,
+            master_volume:1.0,
+            sfx_volume:0.8,
+            music_volume:0.5,
+            wave_mode:false,
+        }
+    }
+
+    // legge le impostazioni salvate (righe "chiave = valore"), le chiavi mancanti restano al valore di default.
+    // wave_mode non viene salvata, si sceglie all'avvio
+    pub fn load(path:&str) -> Result<Self, String>{
+        let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut settings = GameSettings::new();
+
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')){
+            let parsed = line.split_once('=').map(|(key, value)| (key.trim(), value.trim()));
+            match parsed{
+                Some(("screen_shake", value)) => settings.screen_shake = value.parse().unwrap_or(settings.screen_shake),
+                Some(("hit_stop", value)) => settings.hit_stop = value.parse().unwrap_or(settings.hit_stop),
+                Some(("aim_toggle_required", value)) => settings.aim_toggle_required = value.parse().unwrap_or(settings.aim_toggle_required),
+                Some(("master_volume", value)) => settings.master_volume = value.parse().unwrap_or(settings.master_volume),
+                Some(("sfx_volume", value)) => settings.sfx_volume = value.parse().unwrap_or(settings.sfx_volume),
+                Some(("music_volume", value)) => settings.music_volume = value.parse().unwrap_or(settings.music_volume),
+                _ => println!("{}: riga non valida: {}", path, line),
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save(&self, path:&str) -> Result<(), String>{
+        let content = format!("screen_shake = {}\nhit_stop = {}\naim_toggle_required = {}\nmaster_volume = {}\nsfx_volume = {}\nmusic_volume = {}\n",
+            self.screen_shake, self.hit_stop, self.aim_toggle_required, self.master_volume, self.sfx_volume, self.music_volume);
+        std::fs::write(path, content).map_err(|error| error.to_string())
+    }
+
+    // impostazioni on/off modificabili dal menu di pausa, indice da 0 a TOGGLE_COUNT - 1
+    pub const TOGGLE_COUNT:usize = 3;
+
+    pub fn get_toggle(&self, index:usize) -> (&'static str, bool){
+        match index{
+            0 => ("Screen shake", self.screen_shake),
+            1 => ("Hit-stop", self.hit_stop),
+            _ => ("Aim toggle to fire", self.aim_toggle_required),
+        }
+    }
+
+    pub fn flip_toggle(&mut self, index:usize){
+        match index{
+            0 => self.screen_shake = !self.screen_shake,
+            1 => self.hit_stop = !self.hit_stop,
+            _ => self.aim_toggle_required = !self.aim_toggle_required,
+        }
+    }
+}
+
 pub struct Utils{
     mouse_position:Point,
     player_position:FPoint,
     pub main_camera_position:FPoint,
+    pub main_camera_rotation:f64, // rotazione della vista in gradi (screen shake)
+    pub main_camera_zoom:f32,
+    pub gamepad_aim:Option<FPoint>, // direzione dello stick destro se e' la sorgente di mira attuale, altrimenti si usa il mouse
+    pub walls:Vec<FRect>, // muri (rettangoli in world space) che bloccano player, nemici e proiettili
+    pub screen_size:(u32, u32), // dimensione della vista in cui si disegna, aggiornata ad ogni frame
+    pub enemy_positions:Vec<FPoint>, // posizioni di nemici e boss, aggiornate ad ogni frame (es. proiettili a ricerca)
 }
 
 impl Utils{
@@ -373,33 +1203,146 @@ impl Utils{
             mouse_position: Point::new(0, 0),
             player_position: FPoint::new(0.0, 0.0),
             main_camera_position: FPoint::new(0.0, 0.0),
+            main_camera_rotation: 0.0,
+            main_camera_zoom: 1.0,
+            gamepad_aim: None,
+            walls: Vec::new(),
+            screen_size: (0, 0),
+            enemy_positions: Vec::new(),
         }
     }
 
-    pub fn utils_manage_events(&mut self, event:&Event) -> (){
-        match event{
-            Event::MouseMotion { x, y, xrel, yrel , ..} =>{
-                self.mouse_position = Point::new(*x, *y); // aggiorno mouse position
-            }
+    // porta un punto dal world space allo schermo: la posizione relativa al centro della vista viene
+    // scalata dallo zoom e ruotata della rotazione della camera, poi riportata rispetto al centro dello schermo
+    pub fn world_to_screen(&self, world_position:FPoint) -> FPoint{
+        let half_screen = FPoint::new(self.screen_size.0 as f32 / 2.0, self.screen_size.1 as f32 / 2.0);
+        let view_center = self.main_camera_position + half_screen;
+        let from_center = Utils::rotate_point(world_position - view_center, self.main_camera_rotation) * self.main_camera_zoom;
 
-            _ => {
+        half_screen + from_center
+    }
 
-            }
-        }
+    // trasformazione inversa di world_to_screen (es. per la posizione del mouse)
+    pub fn screen_to_world(&self, screen_position:FPoint) -> FPoint{
+        let half_screen = FPoint::new(self.screen_size.0 as f32 / 2.0, self.screen_size.1 as f32 / 2.0);
+        let view_center = self.main_camera_position + half_screen;
+        let from_center = Utils::rotate_point(screen_position - half_screen, -self.main_camera_rotation) / self.main_camera_zoom;
+
+        view_center + from_center
     }
 
-    pub fn save_player_position(&mut self, player_position:FPoint) -> (){
-        self.player_position = player_position;
+    // true se un cerchio (es. l'area di collisione di un'entity) tocca almeno un muro
+    pub fn collides_with_walls(&self, position:FPoint, radius:f32) -> bool{
+        self.walls.iter().any(|wall| {
+            // punto del rettangolo piu' vicino al centro del cerchio
+            let closest = FPoint::new(position.x.clamp(wall.x, wall.x + wall.w), position.y.clamp(wall.y, wall.y + wall.h));
+            Utils::calculate_point_distance(position, closest) < radius
+        })
     }
 
-    pub fn get_player_position(&self) -> FPoint{
-        self.player_position
+    // rettangolo che contiene tutti i muri, usato come limite del livello (camera, spawn). None se non ci sono muri
+    pub fn get_level_bounds(&self) -> Option<FRect>{
+        let first = *self.walls.first()?;
+        Some(self.walls.iter().skip(1).fold(first, |bounds, wall| {
+            let left = bounds.x.min(wall.x);
+            let top = bounds.y.min(wall.y);
+            FRect::new(left, top, (bounds.x + bounds.w).max(wall.x + wall.w) - left, (bounds.y + bounds.h).max(wall.y + wall.h) - top)
+        }))
     }
 
-    // funzioni "statiche" quindi senza self, utilizzata per fare operazioni di utilita' generica
-    // possono essere richiamate semplicemente con Utils::point_magnitude()
-    // non avendo self non dipendono dall'istanza utils creata e, non ritornando Self, non ne creano una
-    pub fn point_magnitude(vector:FPoint) -> f32{
+    // riporta una posizione dentro il livello, a distanza margin dal bordo (es. per non spawnare fuori dai muri esterni)
+    pub fn clamp_to_level(&self, position:FPoint, margin:f32) -> FPoint{
+        match self.get_level_bounds(){
+            Some(bounds) if bounds.w > margin * 2.0 && bounds.h > margin * 2.0 => FPoint::new(
+                position.x.clamp(bounds.x + margin, bounds.x + bounds.w - margin),
+                position.y.clamp(bounds.y + margin, bounds.y + bounds.h - margin)),
+            _ => position,
+        }
+    }
+
+    // sposta un cerchio da from a to fermandolo sui muri. Gli assi vengono risolti separatamente, cosi' contro un muro
+    // si scivola lungo di esso invece di fermarsi. Movimenti lunghi (es. dash) vengono divisi in passi piu' piccoli
+    // del raggio per non attraversare i muri sottili
+    pub fn move_with_walls(&self, from:FPoint, to:FPoint, radius:f32) -> FPoint{
+        // se si parte gia' dentro un muro (es. nemico spawnato sopra un muro) non si blocca, cosi' puo' uscirne
+        if self.walls.is_empty() || self.collides_with_walls(from, radius){
+            return to;
+        }
+
+        let movement = to - from;
+        let steps = (Utils::point_magnitude(movement) / (radius * 0.5).max(1.0)).ceil().max(1.0) as i32;
+        let step = movement / steps as f32;
+
+        let mut position = from;
+        for _ in 0..steps{
+            let moved_x = FPoint::new(position.x + step.x, position.y);
+            if !self.collides_with_walls(moved_x, radius){
+                position = moved_x;
+            }
+            let moved_y = FPoint::new(position.x, position.y + step.y);
+            if !self.collides_with_walls(moved_y, radius){
+                position = moved_y;
+            }
+        }
+
+        position
+    }
+
+    // direzione di mira a partire da una posizione in world space (stick destro oppure verso il mouse)
+    pub fn get_aim_direction(&self, from:FPoint) -> FPoint{
+        match self.gamepad_aim{
+            Some(direction) => direction,
+            None => Utils::point_normalized(self.get_mouse_world_position() - from),
+        }
+    }
+
+    pub fn get_mouse_world_position(&self) -> FPoint{
+        self.screen_to_world(FPoint::new(self.mouse_position.x as f32, self.mouse_position.y as f32))
+    }
+
+    // rotazione di un vettore di angle gradi (in senso orario sullo schermo, come copy_ex)
+    pub fn rotate_point(vector:FPoint, angle:f64) -> FPoint{
+        if angle == 0.0{
+            return vector;
+        }
+
+        let (sin, cos) = (angle.to_radians().sin() as f32, angle.to_radians().cos() as f32);
+        FPoint::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos)
+    }
+
+    // dimensione in cui si disegna: se e' impostata una risoluzione logica si usa quella
+    // (SDL poi la scala sulla finestra), altrimenti la dimensione reale del canvas
+    pub fn view_size(canvas:&WindowCanvas) -> Result<(u32, u32), String>{
+        match canvas.logical_size(){
+            (0, 0) => canvas.output_size(),
+            logical_size => Ok(logical_size),
+        }
+    }
+
+    pub fn utils_manage_events(&mut self, event:&Event) -> (){
+        match event{
+            Event::MouseMotion { x, y, xrel, yrel , ..} =>{
+                self.mouse_position = Point::new(*x, *y); // aggiorno mouse position
+            }
+
+            _ => {
+
+            }
+        }
+    }
+
+    pub fn save_player_position(&mut self, player_position:FPoint) -> (){
+        self.player_position = player_position;
+    }
+
+    pub fn get_player_position(&self) -> FPoint{
+        self.player_position
+    }
+
+    // funzioni "statiche" quindi senza self, utilizzata per fare operazioni di utilita' generica
+    // possono essere richiamate semplicemente con Utils::point_magnitude()
+    // non avendo self non dipendono dall'istanza utils creata e, non ritornando Self, non ne creano una
+    pub fn point_magnitude(vector:FPoint) -> f32{
         (vector.x * vector.x + vector.y * vector.y).sqrt()
     }
 
@@ -418,22 +1361,55 @@ impl Utils{
         ((v2.x - v1.x) * (v2.x - v1.x) + (v2.y - v1.y) * (v2.y - v1.y)).sqrt()
     }
 
-    // scrittura
-    pub fn write_on_screen(to_write:&str, canvas:&mut WindowCanvas, font:&Font, resource_manager:&ResourceManager, pos:Point, size:Point) -> Result<(), String>{
-        let surface = font.render(to_write)
-        .blended(Color::RGB(255, 255, 255)).map_err(|error| {
-            error.to_string()
-        })?; // si propaga errore
+    // interpolazione lineare tra due colori (alpha compreso), t tra 0.0 e 1.0
+    pub fn lerp_color(from:Color, to:Color, t:f32) -> Color{
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a:u8, b:u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::RGBA(lerp(from.r, to.r), lerp(from.g, to.g), lerp(from.b, to.b), lerp(from.a, to.a))
+    }
+
+    // barra (es. della vita) disegnata in world space, centrata in world_center e spostata nel S.R della camera
+    pub fn draw_world_bar(canvas:&mut WindowCanvas, game_utils:&Utils, world_center:FPoint, size:(u32, u32), fill:f32,
+        fill_color:Color, background_color:Color) -> Result<(), String>{
+        let screen_center = game_utils.world_to_screen(world_center);
+        let size = (
+            (size.0 as f32 * game_utils.main_camera_zoom).round().max(1.0) as u32,
+            (size.1 as f32 * game_utils.main_camera_zoom).round().max(1.0) as u32,
+        );
+        let background_rect = Rect::new(
+            (screen_center.x - size.0 as f32 / 2.0).round() as i32,
+            (screen_center.y - size.1 as f32 / 2.0).round() as i32,
+            size.0,
+            size.1,
+        );
+
+        // blend mode necessario per la trasparenza (fade della barra)
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(background_color);
+        canvas.fill_rect(background_rect)?;
+
+        let fill_width = (size.0 as f32 * fill.clamp(0.0, 1.0)).round() as u32;
+        if fill_width > 0{
+            canvas.set_draw_color(fill_color);
+            canvas.fill_rect(Rect::new(background_rect.x(), background_rect.y(), fill_width, size.1))?;
+        }
+        canvas.set_blend_mode(BlendMode::None);
+
+        Ok(())
+    }
 
-        // si crea la texture "dell'immagine" creata con il font
-        let font_texture = resource_manager.get_texture_from_surface(surface)?;
+    // scrittura
+    // la texture del testo viene presa dalla cache del resource manager, quindi si renderizza solo se il testo cambia
+    pub fn write_on_screen(to_write:&str, canvas:&mut WindowCanvas, resource_manager:&mut ResourceManager, font:(&str, u16), color:Color, pos:Point, size:Point) -> Result<(), String>{
+        let (font_name, font_size) = font;
+        let font_texture = resource_manager.get_text_texture(font_name, font_size, color, to_write)?;
 
-            // creo il target come Rect, che specifica la posizione e la grandezza della zona in cui voglio spiaccicare
+        // creo il target come Rect, che specifica la posizione e la grandezza della zona in cui voglio spiaccicare
         // la texture
         let target = Rect::new(pos.x, pos.y, size.x as u32, size.y as u32);
         // faccio canvas.copy() per mettere la scritta nel canvas
         // (texture da mettere, None = voglio mettere tutta la texture, Dove e quanto grande deve essere)
-        canvas.copy(&font_texture, None, Some(target))?;
+        canvas.copy(font_texture, None, Some(target))?;
 
         Ok(())
     }
@@ -446,8 +1422,45 @@ pub struct Player{
     current_fire_rate: f32,
     health:i32,
     current_health:i32,
+    recent_damage:i32, // danno preso dall'ultimo take_recent_damage(), usato da Game per il feedback (shake, hit-stop)
+    pub aim_toggle_required:bool, // true -> bisogna entrare in PlayerState::Shoot (ToggleAim) prima di poter sparare
+
+    // dash: scatto veloce lungo la direzione di movimento, durante il quale il player non prende danno
+    pub dash_speed:f32, // pixel al secondo durante il dash
+    pub dash_duration:f32,
+    pub dash_cooldown:f32,
+    pub dash_invulnerability:f32, // durata dell'invulnerabilita' dall'inizio del dash (puo' durare piu' del dash)
+    dash_time_left:f32,
+    dash_cooldown_left:f32,
+    dash_direction:FPoint,
+    invulnerable_time_left:f32, // finche' > 0 take_damage viene ignorato
+
+    pub hit_invulnerability:f32, // secondi di invulnerabilita' dopo essere stati colpiti (lo sprite lampeggia)
+    knockback_velocity:FPoint, // spinta ricevuta, si riduce nel tempo
+
+    pub resistances:Resistances,
+    pub damage_pipeline:DamagePipeline, // scudi, armature e buff del player
+
+    // effetti dei pickup
+    base_speed:f32, // velocita' senza potenziamenti
+    rapid_fire_time_left:f32, // finche' > 0 si spara al doppio della cadenza
+    speed_boost_time_left:f32,
+    bullet_power:Option<(BulletPower, f32)>, // (potenziamento, secondi rimasti), uno alla volta
+    pub explosive_ammo:i32, // colpi esplosivi rimasti, usati prima di quelli normali
+    pub currency:i32,
+    pub weapon:Weapon,
 }
 
+const PLAYER_EXPLOSIVE_AMMO_EXPLOSION:(f32, i32) = (45.0, 15); // (raggio, danno) dei colpi esplosivi
+
+const PLAYER_RAPID_FIRE_MULTIPLIER:f32 = 0.5; // moltiplicatore del tempo tra due colpi
+const PLAYER_SPEED_BOOST_MULTIPLIER:f32 = 1.5;
+
+const PLAYER_KNOCKBACK_DRAG:f32 = 10.0; // rallentamento al secondo della spinta
+const PLAYER_FLASH_INTERVAL:f32 = 0.08; // secondi tra un lampeggio e l'altro durante l'invulnerabilita' 
+
+const PLAYER_COLLISION_RADIUS:f32 = 15.0;
+
 impl Player{
     pub fn new(name:&str, _speed:f32, _health:i32) -> Self{
         Player { player_entity: Entity::with_speed(name, _speed, EntityType::Player),
@@ -455,7 +1468,28 @@ impl Player{
         fire_rate: 0.5, // fire rate di base
         current_fire_rate: 0.0,
         health: _health,
-        current_health: _health, }
+        current_health: _health,
+        recent_damage: 0,
+        aim_toggle_required: false,
+        dash_speed: 450.0,
+        dash_duration: 0.15,
+        dash_cooldown: 1.0,
+        dash_invulnerability: 0.25,
+        dash_time_left: 0.0,
+        dash_cooldown_left: 0.0,
+        dash_direction: FPoint::new(0.0, 0.0),
+        invulnerable_time_left: 0.0,
+        hit_invulnerability: 1.0,
+        knockback_velocity: FPoint::new(0.0, 0.0),
+        resistances: Resistances::none(),
+        damage_pipeline: DamagePipeline::new(),
+        base_speed: _speed,
+        rapid_fire_time_left: 0.0,
+        speed_boost_time_left: 0.0,
+        bullet_power: None,
+        explosive_ammo: 0,
+        currency: 0,
+        weapon: Weapon::Pistol, }
     }
 
     pub fn with_fire_rate(name:&str, _speed:f32, _health:i32, initial_fire_rate:f32) -> Self{
@@ -464,91 +1498,158 @@ impl Player{
             fire_rate: initial_fire_rate,
             current_fire_rate: 0.0,
             health: _health,
-            current_health: _health, }
+            current_health: _health,
+            recent_damage: 0,
+            aim_toggle_required: false,
+            dash_speed: 450.0,
+            dash_duration: 0.15,
+            dash_cooldown: 1.0,
+            dash_invulnerability: 0.25,
+            dash_time_left: 0.0,
+            dash_cooldown_left: 0.0,
+            dash_direction: FPoint::new(0.0, 0.0),
+            invulnerable_time_left: 0.0,
+            hit_invulnerability: 1.0,
+            knockback_velocity: FPoint::new(0.0, 0.0),
+            resistances: Resistances::none(),
+            damage_pipeline: DamagePipeline::new(),
+            base_speed: _speed,
+            rapid_fire_time_left: 0.0,
+            speed_boost_time_left: 0.0,
+            bullet_power: None,
+            explosive_ammo: 0,
+            currency: 0,
+            weapon: Weapon::Pistol, }
     }
 
-    pub fn player_controller(&mut self, event:&Event, gameobjects_list:&mut Vec<Box<dyn GameObject>>){
-        match event{
-            Event::KeyDown { keycode:Some(Keycode::F), .. } =>{
-                //if self.player_state != PlayerState::Interaction{
-                //    self.player_state = PlayerState::Shoot;
-                //}
-                if self.player_state != PlayerState::Shoot{
-                    self.player_state = PlayerState::Shoot;
-                }else{
-                    self.player_state = PlayerState::Idle; // torno in idle se premo F in shoot
-                }
-            },
-            Event::MouseButtonDown { mouse_btn:MouseButton::Left, .. } => {
-                if self.player_state == PlayerState::Shoot{
-
-                    if self.current_fire_rate <= 0.0{ // solo se posso sparare 
-                    
-                    // si crea nuovo bullet
-                    let bullet_velocity = 200.0;
-                    let bullet_direction = self.player_entity.get_forward_direction();
-                    
-                    // per spostare la posizione del bullet rispetto al sistema di riferimento locale del player
-                    // devo ottenere il suo S.R.L 
-                    // ottengo quindi il forward che sarebbe la direzione in cui punta il player
-                    // ottengo l'asse right mediante rotazione di 90 gradi in senso orario
-                    let forward = self.player_entity.get_forward_direction();
-                    let right = self.player_entity.get_right_direction();
-                    let bullet_offset = forward * 20.0 + right * -12.0; // sposto in avanti di 20 pixel e a sinistra di 12
-                    
-                    // quindi parto dal player e sposto il bullet dell'offset, calcolato nel S.R.L del player, quindi
-                    // quando il player si sposta rimane invariato
-                    let bullet_starting_position = self.player_entity.position + bullet_offset;
-
-                    let new_bullet = Bullet::new(bullet_direction
-                        , self.player_entity.entity_type, bullet_velocity, bullet_starting_position);
-                    
-                    // si mette bullet nella lista dei gameobjects di game
-                    gameobjects_list.push(Box::new(new_bullet)); // si crea una copia nello heap di new_bullet
-                    // alla fine del metodo new_bullet dichiarato qui su viene automaticamente droppato, mentre quello nella gameobjects_list rimane appunto nello heap
-                    
-                    self.current_fire_rate = self.fire_rate; // resetto il current_fire_rate 
-                    }
-                }
+    // eseguito una volta per frame con lo stato dell'input, le azioni dipendono dai binding configurati
+    pub fn player_controller(&mut self, input:&InputState, bullets:&mut Pool<Bullet>, particles:&mut ParticleSystem,
+        audio:&mut AudioManager){
+        if input.is_action_just_pressed(InputAction::ToggleAim){
+            //if self.player_state != PlayerState::Interaction{
+            //    self.player_state = PlayerState::Shoot;
+            //}
+            if self.player_state != PlayerState::Shoot{
+                self.player_state = PlayerState::Shoot;
+            }else{
+                self.player_state = PlayerState::Idle; // torno in idle se premo F in shoot
             }
+        }
 
-            _ => {
+        if input.is_action_just_pressed(InputAction::Dash){
+            self.start_dash();
+        }
+
+        if input.is_action_just_pressed(InputAction::SwitchWeapon){
+            self.weapon = self.weapon.next();
+        }
+
+        // sparo automatico finche' l'azione Fire e' tenuta premuta, la cadenza e' data da fire_rate.
+        // Se non e' richiesta la modalita' mira (impostazioni) si puo' sparare sempre
+        let can_fire = !self.aim_toggle_required || self.player_state == PlayerState::Shoot;
+        if input.is_action_held(InputAction::Fire) && can_fire{
+            self.player_state = PlayerState::Shoot; // sprite con l'arma in mano
+            self.shoot(bullets, particles, audio);
+        }
+    }
+
+    fn shoot(&mut self, bullets:&mut Pool<Bullet>, particles:&mut ParticleSystem, audio:&mut AudioManager){
+        if self.current_fire_rate > 0.0{ // solo se posso sparare 
+            return;
+        }
 
+        // si crea nuovo bullet
+        let bullet_velocity = 200.0;
+        let bullet_direction = self.player_entity.get_forward_direction();
+        
+        // per spostare la posizione del bullet rispetto al sistema di riferimento locale del player
+        // devo ottenere il suo S.R.L 
+        // ottengo quindi il forward che sarebbe la direzione in cui punta il player
+        // ottengo l'asse right mediante rotazione di 90 gradi in senso orario
+        let forward = self.player_entity.get_forward_direction();
+        let right = self.player_entity.get_right_direction();
+        let bullet_offset = forward * 20.0 + right * -12.0; // sposto in avanti di 20 pixel e a sinistra di 12
+        
+        // quindi parto dal player e sposto il bullet dell'offset, calcolato nel S.R.L del player, quindi
+        // quando il player si sposta rimane invariato
+        let bullet_starting_position = self.player_entity.position + bullet_offset;
+
+        // il bullet si prende dal pool di game (riciclato se possibile) e si reinizializza
+        if let Some(new_bullet) = bullets.acquire(){
+            new_bullet.reset(bullet_direction, self.player_entity.entity_type, bullet_velocity, bullet_starting_position);
+            new_bullet.owner_id = Some(self.player_entity.get_id());
+
+            new_bullet.damage_type = self.weapon.get_damage_type();
+            new_bullet.modifiers = self.weapon.get_modifiers();
+            if let Some((power, _)) = self.bullet_power{
+                new_bullet.modifiers = power.apply(new_bullet.modifiers);
+            }
+            if self.explosive_ammo > 0{
+                // i colpi esplosivi si sommano ai modificatori dell'arma
+                new_bullet.damage_type = DamageType::Explosive;
+                new_bullet.modifiers = new_bullet.modifiers.with_explosion(PLAYER_EXPLOSIVE_AMMO_EXPLOSION.0, PLAYER_EXPLOSIVE_AMMO_EXPLOSION.1);
+                self.explosive_ammo -= 1;
             }
         }
+
+        particles.emit(&ParticleEmitterConfig::muzzle_flash(), bullet_starting_position, bullet_direction);
+        audio.play_at("shoot", bullet_starting_position);
+        
+        self.current_fire_rate = self.fire_rate; // resetto il current_fire_rate 
+        if self.rapid_fire_time_left > 0.0{
+            self.current_fire_rate *= PLAYER_RAPID_FIRE_MULTIPLIER;
+        }
     }
 
-    pub fn move_player(&mut self, event:&Event){
-        match event {
-            Event::KeyDown { keycode:Some(Keycode::A), .. } =>{
-                // cambio direzione lungo x e non lungo y
-                self.player_entity.change_direction(FPoint::new(-1.0,self.player_entity.movement_direction.y));
+    // applica l'effetto di un pickup raccolto
+    pub fn collect_pickup(&mut self, kind:PickupKind){
+        match kind{
+            PickupKind::Health(amount) => {
+                self.current_health = (self.current_health + amount).min(self.health);
             },
-            Event::KeyDown { keycode:Some(Keycode::D), .. } =>{
-                // cambio direzione lungo x e non lungo y
-                self.player_entity.change_direction(FPoint::new(1.0,self.player_entity.movement_direction.y));
+            PickupKind::Ammo(amount) => {
+                self.explosive_ammo += amount;
             },
-            Event::KeyDown { keycode:Some(Keycode::S), .. } =>{
-                // cambio direzione lungo x e non lungo y
-                self.player_entity.change_direction(FPoint::new(self.player_entity.movement_direction.x, 1.0));
+            PickupKind::RapidFire(duration) => {
+                self.rapid_fire_time_left = self.rapid_fire_time_left.max(duration);
             },
-            Event::KeyDown { keycode:Some(Keycode::W), .. } =>{
-                // cambio direzione lungo x e non lungo y
-                self.player_entity.change_direction(FPoint::new(self.player_entity.movement_direction.x, -1.0));
+            PickupKind::SpeedBoost(duration) => {
+                self.speed_boost_time_left = self.speed_boost_time_left.max(duration);
             },
-            Event::KeyUp { keycode:Some(Keycode::A), ..} |
-            Event::KeyUp { keycode:Some(Keycode::D), ..} => {
-                self.player_entity.change_direction(FPoint::new(0.0, self.player_entity.movement_direction.y));
+            PickupKind::Currency(amount) => {
+                self.currency += amount;
             },
-            Event::KeyUp { keycode:Some(Keycode::S), ..} |
-            Event::KeyUp { keycode:Some(Keycode::W), ..} => {
-                self.player_entity.change_direction(FPoint::new(self.player_entity.movement_direction.x, 0.0));
+            PickupKind::BulletPower(power, duration) => {
+                // un nuovo potenziamento sostituisce quello attuale, lo stesso ne allunga la durata
+                let time_left = match self.bullet_power{
+                    Some((current, time_left)) if current == power => time_left.max(duration),
+                    _ => duration,
+                };
+                self.bullet_power = Some((power, time_left));
             },
+        }
+    }
 
-            _ => {
+    pub fn get_bullet_power(&self) -> Option<(BulletPower, f32)>{
+        self.bullet_power
+    }
 
-            }
-        }
+    // direzione di movimento calcolata dai tasti tenuti premuti in questo frame: tasti opposti si annullano,
+    // rilasciare un tasto non ferma l'altro, e in diagonale la velocita' resta la stessa (direzione normalizzata)
+    pub fn move_player(&mut self, input:&InputState){
+        let axis = |negative:InputAction, positive:InputAction| {
+            input.is_action_held(positive) as i32 as f32 - input.is_action_held(negative) as i32 as f32
+        };
+
+        let direction = FPoint::new(axis(InputAction::MoveLeft, InputAction::MoveRight), axis(InputAction::MoveUp, InputAction::MoveDown));
+
+        // lo stick sinistro (gia' filtrato dalla dead zone) ha la precedenza sui tasti
+        let direction = if input.get_move_stick() != FPoint::new(0.0, 0.0){
+            input.get_move_stick()
+        }else{
+            direction
+        };
+        self.player_entity.change_direction(Utils::point_normalized(direction));
     }
 
     pub fn set_fire_rate(&mut self, new_fire_rate:f32){
@@ -558,12 +1659,52 @@ impl Player{
     pub fn get_fire_rate(&self) -> f32{
         self.fire_rate
     }
+
+    // il dash parte nella direzione di movimento, se il player e' fermo in quella in cui sta mirando
+    fn start_dash(&mut self){
+        if self.dash_cooldown_left > 0.0 || self.is_dashing(){
+            return;
+        }
+
+        self.dash_direction = if self.player_entity.movement_direction != FPoint::new(0.0, 0.0){
+            Utils::point_normalized(self.player_entity.movement_direction)
+        }else{
+            self.player_entity.get_forward_direction()
+        };
+        self.dash_time_left = self.dash_duration;
+        self.dash_cooldown_left = self.dash_cooldown;
+        self.invulnerable_time_left = self.invulnerable_time_left.max(self.dash_invulnerability);
+    }
+
+    pub fn is_dashing(&self) -> bool{
+        self.dash_time_left > 0.0
+    }
+
+    // ricarica del dash tra 0.0 (appena usato) e 1.0 (pronto), mostrata nell'HUD
+    pub fn get_dash_charge(&self) -> f32{
+        if self.dash_cooldown <= 0.0{
+            1.0
+        }else{
+            (1.0 - self.dash_cooldown_left / self.dash_cooldown).clamp(0.0, 1.0)
+        }
+    }
+
+    // restituisce il danno preso dall'ultima chiamata e lo azzera
+    pub fn take_recent_damage(&mut self) -> i32{
+        std::mem::take(&mut self.recent_damage)
+    }
 }
 
 // Implemento GameObject per player utilizzando le funzioni di player_entity
 impl GameObject for Player{
     fn draw(&mut self, canvas:&mut WindowCanvas, texture:&Texture, animation_frame:u32, game_utils:&Utils, scale_factor:f32) -> Result<(), String> {
 
+        // lampeggio durante l'invulnerabilita' dopo un colpo (non durante il dash): un intervallo si', uno no
+        if self.is_invulnerable() && !self.is_dashing()
+            && (self.invulnerable_time_left / PLAYER_FLASH_INTERVAL) as i32 % 2 == 1{
+            return Ok(());
+        }
+
         self.player_entity.draw(canvas, texture, self.player_state as u32, game_utils, scale_factor)?;
 
         Ok(())
@@ -574,21 +1715,50 @@ impl GameObject for Player{
     }
 
     fn update(&mut self, deltatime:f32, game_utils:&Utils) {
-        self.player_entity.update(deltatime, game_utils);
+        self.player_entity.speed = if self.speed_boost_time_left > 0.0 { self.base_speed * PLAYER_SPEED_BOOST_MULTIPLIER } else { self.base_speed };
+        self.speed_boost_time_left = (self.speed_boost_time_left - deltatime).max(0.0);
+        self.rapid_fire_time_left = (self.rapid_fire_time_left - deltatime).max(0.0);
+        self.bullet_power = self.bullet_power
+            .map(|(power, time_left)| (power, time_left - deltatime))
+            .filter(|(_, time_left)| *time_left > 0.0);
+
+        if self.is_dashing(){
+            // durante il dash si ignora l'input di movimento, i muri fermano comunque lo scatto
+            let target = self.player_entity.position + self.dash_direction * self.dash_speed * deltatime;
+            self.player_entity.position = game_utils.move_with_walls(self.player_entity.position, target, PLAYER_COLLISION_RADIUS);
+            self.dash_time_left -= deltatime;
+        }else{
+            self.player_entity.update_with_walls(deltatime, game_utils, PLAYER_COLLISION_RADIUS);
+        }
 
+        // knockback: spinta che si somma al movimento e si smorza nel tempo, anche lei fermata dai muri
+        if self.knockback_velocity != FPoint::new(0.0, 0.0){
+            let target = self.player_entity.position + self.knockback_velocity * deltatime;
+            self.player_entity.position = game_utils.move_with_walls(self.player_entity.position, target, PLAYER_COLLISION_RADIUS);
+
+            self.knockback_velocity *= (1.0 - PLAYER_KNOCKBACK_DRAG * deltatime).max(0.0);
+            if Utils::point_magnitude(self.knockback_velocity) < 1.0{
+                self.knockback_velocity = FPoint::new(0.0, 0.0);
+            }
+        }
+
+        if self.dash_cooldown_left > 0.0{
+            self.dash_cooldown_left -= deltatime;
+        }
+        if self.invulnerable_time_left > 0.0{
+            self.invulnerable_time_left -= deltatime;
+        }
+
+        // con il movimento (e lo zoom) della camera rispetto al player, la posizione del mouse va riportata
+        // in world space con la trasformazione inversa della camera. Se si sta mirando con lo stick destro
+        // si usa direttamente la sua direzione (vedi Utils::get_aim_direction)
+        let aim_target = self.player_entity.position + game_utils.get_aim_direction(self.player_entity.position);
         
-        // con il movimento della camera rispetto al player, la posizione del mouse deve essere sommata alla posizione
-        // della camera
-        let mouse_world_position = FPoint::new(
-            game_utils.mouse_position.x as f32 + game_utils.main_camera_position.x,
-            game_utils.mouse_position.y as f32 + game_utils.main_camera_position.y,
-        );
-        
-        // posizione del mouse relativa al player -> (target - player) -> poi uso atan2
-        let relative_mouse_position = mouse_world_position - self.player_entity.position;
+        // posizione del target relativa al player -> (target - player) -> poi uso atan2
+        let relative_mouse_position = aim_target - self.player_entity.position;
         
         // rotazione sempre mediante atan2
-        let player_rotation = (relative_mouse_position.y as f64).atan2((relative_mouse_position.x as f64)).to_degrees();
+        let player_rotation = (relative_mouse_position.y as f64).atan2(relative_mouse_position.x as f64).to_degrees();
         self.player_entity.rotation = player_rotation;
 
         if self.current_fire_rate > 0.0{
@@ -618,8 +1788,33 @@ impl Damageable for Player{
         self.current_health
     }
 
-    fn take_damage(&mut self, damage:i32) {
-        self.current_health -= damage;
+    fn get_max_health(&self) -> i32 {
+        self.health
+    }
+
+    fn take_damage(&mut self, damage:&DamageInfo) -> DamageInfo {
+        if self.is_invulnerable(){
+            return DamageInfo{ amount:0, ..*damage }; // es. durante il dash o subito dopo un altro colpo
+        }
+
+        let resistances = self.get_resistances();
+        let resolved = self.damage_pipeline.resolve(damage, &resistances);
+        self.current_health -= resolved.amount;
+        self.recent_damage += resolved.amount;
+        self.invulnerable_time_left = self.hit_invulnerability;
+        resolved
+    }
+
+    fn get_resistances(&self) -> Resistances {
+        self.resistances
+    }
+
+    fn is_invulnerable(&self) -> bool {
+        self.invulnerable_time_left > 0.0
+    }
+
+    fn apply_knockback(&mut self, direction:FPoint, strength:f32) {
+        self.knockback_velocity = direction * strength;
     }
 
     fn get_entity(&self) -> &Entity {
@@ -634,9 +1829,12 @@ pub struct Entity{
     pub movement_direction:FPoint,
     entity_sprite:Sprite,
     entity_name:String,
-    entity_type:EntityType
+    entity_type:EntityType,
+    id:u32, // identificativo univoco, usato ad esempio come sorgente del danno
 }
 
+static NEXT_ENTITY_ID:AtomicU32 = AtomicU32::new(0);
+
 impl Entity{
     // passo dimensione dello sprite
     pub fn new(name:&str, _entity_type:EntityType) -> Self{
@@ -648,6 +1846,7 @@ impl Entity{
             entity_name: name.to_string(),
             entity_type: _entity_type,
             rotation:0.0,
+            id: NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed),
         }
     }
 
@@ -660,9 +1859,14 @@ impl Entity{
             entity_name: name.to_string(),
             entity_type: _entity_type,
             rotation:0.0,
+            id: NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed),
         }
     }
 
+    pub fn get_id(&self) -> u32{
+        self.id
+    }
+
     pub fn change_direction(&mut self, direction:FPoint){
         self.movement_direction = direction;
     }
@@ -687,6 +1891,12 @@ impl Entity{
         }
     }
 
+    // come update() ma il movimento viene fermato dai muri (radius = raggio di collisione dell'entity)
+    pub fn update_with_walls(&mut self, deltatime:f32, game_utils:&Utils, radius:f32){
+        let target = self.position + (self.move_entity() - self.position) * deltatime;
+        self.position = game_utils.move_with_walls(self.position, target, radius);
+    }
+
     pub fn set_sprite(&mut self, frame_width:u32, frame_height:u32){
         self.entity_sprite = Sprite::new(frame_width, frame_height);
     }
@@ -736,12 +1946,20 @@ impl GameObject for Entity{
 
         // sposto nel S.R della camera, quindi sottraggo la posizione della camera alla posizione del gameobject
         // 
-        let entity_screen_position = self.position - game_utils.main_camera_position;
+        let entity_screen_position = game_utils.world_to_screen(self.position);
 
-        // rappresentazione nello schermo dell'entity
+        // rappresentazione nello schermo dell'entity (la dimensione dipende anche dallo zoom della camera)
         let screen_rect = FRect::from_center(entity_screen_position,
-            self.entity_sprite.sprite.width() as f32 * scale_factor,
-            self.entity_sprite.sprite.height() as f32 * scale_factor);
+            self.entity_sprite.sprite.width() as f32 * scale_factor * game_utils.main_camera_zoom,
+            self.entity_sprite.sprite.height() as f32 * scale_factor * game_utils.main_camera_zoom);
+
+        // culling: se lo sprite e' completamente fuori dallo schermo non si disegna
+        // (si usa la diagonale come margine, cosi' anche gli sprite ruotati non spariscono sul bordo)
+        let margin = (screen_rect.width() * screen_rect.width() + screen_rect.height() * screen_rect.height()).sqrt();
+        if screen_rect.x + screen_rect.width() + margin < 0.0 || screen_rect.y + screen_rect.height() + margin < 0.0
+            || screen_rect.x - margin > game_utils.screen_size.0 as f32 || screen_rect.y - margin > game_utils.screen_size.1 as f32{
+            return Ok(());
+        }
         
         // converto in intero
         let output_rect = Rect::new(
@@ -755,8 +1973,9 @@ impl GameObject for Entity{
         // come center fare center:Point = Point::new(output_rect_widht/2, output_rect_height/2)
         let sprite_center:Point = Point::new(output_rect.width() as i32 / 2, output_rect.height() as i32 / 2);
 
+        // alla rotazione dello sprite si somma quella della camera (screen shake)
         canvas.copy_ex(texture, current_frame_in_sprite_sheet, output_rect, 
-            self.get_rotation(), sprite_center, false, false)?;
+            self.get_rotation() + game_utils.main_camera_rotation, sprite_center, false, false)?;
 
 
         // DEBUG
@@ -818,18 +2037,40 @@ impl Sprite{
     }
 }
 
+// chiave della cache dei testi: (nome font, dimensione font, colore, stringa)
+type TextCacheKey = (String, u16, Color, String);
+
+// texture di un testo gia' renderizzato, con il numero dell'ultimo frame in cui e' stata usata
+struct CachedText<'l>{
+    texture:Texture<'l>,
+    last_used_frame:u64,
+}
+
 // per contenere tutte le textures, contiene anche il texture creator per caricarle e restituirle
+// contiene anche i font (caricati una sola volta) e la cache dei testi renderizzati
 pub struct ResourceManager<'l>{
     texture_creator:&'l TextureCreator<WindowContext>,
+    ttf_context:&'l Sdl2TtfContext,
     textures: HashMap<String, Texture<'l>>,
+    fonts: HashMap<(String, u16), Font<'l, 'static>>, // chiave (nome, dimensione)
+    text_cache: HashMap<TextCacheKey, CachedText<'l>>,
+    current_frame:u64,
+    sounds: HashMap<String, Chunk>,
+    music: HashMap<String, Music<'static>>,
 }
 
 impl<'l> ResourceManager<'l>{
-    // passo il texture_creator quando inizializzo Game.rs
-    pub fn new(texture_creator: &'l TextureCreator<WindowContext>) -> Self {
+    // passo il texture_creator e il ttf_context quando inizializzo Game.rs
+    pub fn new(texture_creator: &'l TextureCreator<WindowContext>, ttf_context: &'l Sdl2TtfContext) -> Self {
         Self {
             texture_creator,
+            ttf_context,
             textures: HashMap::new(),
+            fonts: HashMap::new(),
+            text_cache: HashMap::new(),
+            current_frame: 0,
+            sounds: HashMap::new(),
+            music: HashMap::new(),
         }
     }
 
@@ -849,11 +2090,2905 @@ impl<'l> ResourceManager<'l>{
         self.textures.get(nome) // riferimento non mutabile alla texture ovviamente, non si vuole modificare
     } 
 
-    pub fn get_texture_from_surface(&self, surface: Surface) -> Result<Texture, String>{
+    // riferimento mutabile, serve per modificare color/alpha mod e blend mode della texture (es. particelle)
+    pub fn get_texture_mut(&mut self, nome:&str) -> Option<&mut Texture<'l>>{
+        self.textures.get_mut(nome)
+    }
+
+    // per le texture create a runtime (non caricate da file)
+    pub fn insert_texture(&mut self, nome:&str, texture:Texture<'l>){
+        if !self.textures.contains_key(nome){
+            self.textures.insert(nome.to_string(), texture);
+        }else{
+            println!("Texture con nome {} gia' inserita", nome);
+        }
+    }
+
+    pub fn get_texture_from_surface(&self, surface: Surface) -> Result<Texture<'l>, String>{
         let surface_texture = self.texture_creator.create_texture_from_surface(surface).map_err(|err|{
             err.to_string()
         })?;
 
         Ok(surface_texture)
     }
-}
\ No newline at end of file
+
+    // i suoni possono essere caricati solo se l'audio e' stato aperto (vedi AudioManager)
+    pub fn load_sound(&mut self, nome:&str, path:&str) -> Result<(), String>{
+        if self.sounds.contains_key(nome){
+            println!("Suono con nome {} gia' inserito", nome);
+            return Ok(());
+        }
+
+        let sound = Chunk::from_file(path)?;
+        self.sounds.insert(nome.to_string(), sound);
+
+        Ok(())
+    }
+
+    pub fn get_sound(&self, nome:&str) -> Option<&Chunk>{
+        self.sounds.get(nome)
+    }
+
+    pub fn load_music(&mut self, nome:&str, path:&str) -> Result<(), String>{
+        if self.music.contains_key(nome){
+            println!("Musica con nome {} gia' inserita", nome);
+            return Ok(());
+        }
+
+        let music = Music::from_file(path)?;
+        self.music.insert(nome.to_string(), music);
+
+        Ok(())
+    }
+
+    pub fn get_music(&self, nome:&str) -> Option<&Music<'static>>{
+        self.music.get(nome)
+    }
+
+    // il font viene caricato una sola volta (prima veniva ricaricato ad ogni frame in Game.render())
+    // lo stesso file puo' essere caricato con piu' dimensioni, ognuna e' un font diverso per SDL_ttf
+    pub fn load_font(&mut self, nome:&str, path:&str, point_size:u16) -> Result<(), String>{
+        let key = (nome.to_string(), point_size);
+
+        if self.fonts.contains_key(&key){
+            println!("Font con nome {} ({}) gia' inserito", nome, point_size);
+            return Ok(());
+        }
+
+        let mut font = self.ttf_context.load_font(Path::new(path), point_size)?;
+        font.set_style(sdl2::ttf::FontStyle::NORMAL);
+        self.fonts.insert(key, font);
+
+        Ok(())
+    }
+
+    pub fn get_font(&self, nome:&str, point_size:u16) -> Option<&Font<'l, 'static>>{
+        self.fonts.get(&(nome.to_string(), point_size))
+    }
+
+    // restituisce la texture del testo richiesto, la si crea solo se non e' gia' presente nella cache
+    // (quindi solo quando il testo dell'HUD cambia)
+    pub fn get_text_texture(&mut self, font_name:&str, point_size:u16, color:Color, text:&str) -> Result<&Texture<'l>, String>{
+        Ok(self.get_text_texture_mut(font_name, point_size, color, text)?)
+    }
+
+    // come get_text_texture, ma permette di cambiare color/alpha mod (es. numeri del danno renderizzati in bianco
+    // e colorati al momento del disegno). Chi modifica la texture deve poi ripristinarla, e' condivisa dalla cache
+    pub fn get_text_texture_mut(&mut self, font_name:&str, point_size:u16, color:Color, text:&str) -> Result<&mut Texture<'l>, String>{
+        let key:TextCacheKey = (font_name.to_string(), point_size, color, text.to_string());
+
+        if !self.text_cache.contains_key(&key){
+            let font = self.fonts.get(&(font_name.to_string(), point_size))
+                .ok_or(format!("Font {} ({}) non caricato", font_name, point_size))?;
+
+            let surface = font.render(text).blended(color).map_err(|error| {
+                error.to_string()
+            })?; // si propaga errore
+
+            let texture = self.get_texture_from_surface(surface)?;
+            self.text_cache.insert(key.clone(), CachedText { texture, last_used_frame: self.current_frame });
+        }
+
+        let cached = self.text_cache.get_mut(&key).unwrap(); // presente sicuramente, inserito sopra
+        cached.last_used_frame = self.current_frame;
+
+        Ok(&mut cached.texture)
+    }
+
+    // dimensione in pixel che avrebbe il testo renderizzato con il font indicato (usata per il layout dell'HUD)
+    pub fn measure_text(&self, font_name:&str, point_size:u16, text:&str) -> Result<(u32, u32), String>{
+        let font = self.get_font(font_name, point_size)
+            .ok_or(format!("Font {} ({}) non caricato", font_name, point_size))?;
+
+        font.size_of(text).map_err(|error| {
+            error.to_string()
+        })
+    }
+
+    // da chiamare alla fine di ogni render: elimina dalla cache i testi non usati in questo frame
+    // (es. il vecchio valore dello score) in modo che la cache non cresca all'infinito
+    pub fn end_frame(&mut self){
+        let current_frame = self.current_frame;
+        self.text_cache.retain(|_, cached| cached.last_used_frame == current_frame);
+        self.current_frame += 1;
+    }
+}
+
+// ------------- DANNO -------------
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DamageType{
+    Kinetic, // proiettili, contatto
+    Explosive,
+    Fire,
+}
+
+// tutte le informazioni su un colpo, passate a Damageable::take_damage
+#[derive(Clone, Copy, Debug)]
+pub struct DamageInfo{
+    pub amount:i32,
+    pub damage_type:DamageType,
+    pub source:EntityType, // chi ha inflitto il danno
+    pub source_id:Option<u32>, // entity che ha inflitto il danno (Entity::get_id), None se non e' nota
+    pub direction:FPoint, // direzione del colpo (normalizzata), (0,0) se non ha senso
+    pub critical:bool,
+}
+
+impl DamageInfo{
+    pub fn new(amount:i32, damage_type:DamageType, source:EntityType) -> Self{
+        DamageInfo{
+            amount,
+            damage_type,
+            source,
+            source_id:None,
+            direction:FPoint::new(0.0, 0.0),
+            critical:false,
+        }
+    }
+
+    pub fn with_direction(mut self, direction:FPoint) -> Self{
+        self.direction = direction;
+        self
+    }
+
+    pub fn with_critical(mut self, critical:bool) -> Self{
+        self.critical = critical;
+        self
+    }
+
+    pub fn with_source_id(mut self, source_id:Option<u32>) -> Self{
+        self.source_id = source_id;
+        self
+    }
+}
+
+impl DamageType{
+    // colore dei numeri del danno
+    pub fn get_color(&self) -> Color{
+        match self{
+            DamageType::Kinetic => Color::RGB(255, 240, 200),
+            DamageType::Explosive => Color::RGB(255, 150, 40),
+            DamageType::Fire => Color::RGB(255, 70, 40),
+        }
+    }
+}
+
+// frazione di danno ridotta per tipo: 0.0 nessuna riduzione, 1.0 immune, valori negativi = debolezza
+#[derive(Clone, Copy, Debug)]
+pub struct Resistances{
+    pub kinetic:f32,
+    pub explosive:f32,
+    pub fire:f32,
+}
+
+impl Resistances{
+    pub fn new(kinetic:f32, explosive:f32, fire:f32) -> Self{
+        Resistances{ kinetic, explosive, fire }
+    }
+
+    pub fn none() -> Self{
+        Resistances::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn get(&self, damage_type:DamageType) -> f32{
+        match damage_type{
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Explosive => self.explosive,
+            DamageType::Fire => self.fire,
+        }
+    }
+}
+
+// catena di modificatori applicata a ogni colpo: prima le resistenze, poi i modificatori nell'ordine in cui sono stati aggiunti
+pub struct DamagePipeline{
+    modifiers:Vec<Box<dyn DamageModifier>>,
+}
+
+impl DamagePipeline{
+    pub fn new() -> Self{
+        DamagePipeline{ modifiers:Vec::new() }
+    }
+
+    pub fn add_modifier(&mut self, modifier:Box<dyn DamageModifier>){
+        self.modifiers.push(modifier);
+    }
+
+    pub fn clear_modifiers(&mut self){
+        self.modifiers.clear();
+    }
+
+    // restituisce il danno finale (mai negativo) da togliere alla vita
+    pub fn resolve(&mut self, damage:&DamageInfo, resistances:&Resistances) -> DamageInfo{
+        let mut resolved = *damage;
+        let multiplier = (1.0 - resistances.get(damage.damage_type)).max(0.0);
+        resolved.amount = (damage.amount as f32 * multiplier).round() as i32;
+
+        for modifier in self.modifiers.iter_mut(){
+            modifier.modify(&mut resolved);
+        }
+
+        resolved.amount = resolved.amount.max(0);
+        resolved
+    }
+}
+
+// armatura: toglie un valore fisso a ogni colpo, ma un colpo che fa danno ne fa sempre almeno 1
+pub struct ArmorModifier{
+    pub flat:i32,
+}
+
+impl ArmorModifier{
+    pub fn new(flat:i32) -> Self{
+        ArmorModifier{ flat }
+    }
+}
+
+impl DamageModifier for ArmorModifier{
+    fn modify(&mut self, damage:&mut DamageInfo){
+        if damage.amount > 0{
+            damage.amount = (damage.amount - self.flat).max(1);
+        }
+    }
+}
+
+// scudo: assorbe i colpi finche' ha punti, il danno in eccesso passa
+pub struct ShieldModifier{
+    points:i32,
+}
+
+impl ShieldModifier{
+    pub fn new(points:i32) -> Self{
+        ShieldModifier{ points }
+    }
+}
+
+impl DamageModifier for ShieldModifier{
+    fn modify(&mut self, damage:&mut DamageInfo){
+        let absorbed = damage.amount.clamp(0, self.points);
+        self.points -= absorbed;
+        damage.amount -= absorbed;
+    }
+}
+
+// ------------- INPUT -------------
+
+// azioni di gioco, ognuna associata a uno o piu' tasti / bottoni del mouse (vedi InputBindings)
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum InputAction{
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    ToggleAim,
+    Dash,
+    Pause,
+    SwitchWeapon,
+    ToggleDebug, // overlay di debug
+}
+
+impl InputAction{
+    // ordine in cui le azioni compaiono nel file e nel menu di rebinding
+    pub const ALL:[InputAction; 10] = [
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Fire,
+        InputAction::ToggleAim,
+        InputAction::Dash,
+        InputAction::Pause,
+        InputAction::SwitchWeapon,
+        InputAction::ToggleDebug,
+    ];
+
+    // nome usato nel file dei binding
+    pub fn get_name(&self) -> &'static str{
+        match self{
+            InputAction::MoveUp => "MoveUp",
+            InputAction::MoveDown => "MoveDown",
+            InputAction::MoveLeft => "MoveLeft",
+            InputAction::MoveRight => "MoveRight",
+            InputAction::Fire => "Fire",
+            InputAction::ToggleAim => "ToggleAim",
+            InputAction::Dash => "Dash",
+            InputAction::Pause => "Pause",
+            InputAction::SwitchWeapon => "SwitchWeapon",
+            InputAction::ToggleDebug => "ToggleDebug",
+        }
+    }
+
+    pub fn from_name(name:&str) -> Option<InputAction>{
+        InputAction::ALL.iter().copied().find(|action| action.get_name() == name)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum InputBinding{
+    Key(Keycode),
+    Mouse(MouseButton),
+    GamepadButton(Button),
+    GamepadAxis(Axis), // asse (es. grilletto) considerato premuto oltre GAMEPAD_AXIS_PRESS_THRESHOLD
+}
+
+impl InputBinding{
+    // formato nel file: nome SDL del tasto (es. "W", "Space"), "Mouse:Left", oppure per il gamepad
+    // "Pad:a" / "PadAxis:righttrigger" (nomi SDL di bottoni e assi)
+    pub fn get_name(&self) -> String{
+        match self{
+            InputBinding::Key(key) => key.name(),
+            InputBinding::Mouse(button) => format!("Mouse:{:?}", button),
+            InputBinding::GamepadButton(button) => format!("Pad:{}", button.string()),
+            InputBinding::GamepadAxis(axis) => format!("PadAxis:{}", axis.string()),
+        }
+    }
+
+    // true per bottoni e assi del gamepad, false per tastiera e mouse
+    pub fn is_gamepad(&self) -> bool{
+        matches!(self, InputBinding::GamepadButton(_) | InputBinding::GamepadAxis(_))
+    }
+
+    pub fn from_name(name:&str) -> Option<InputBinding>{
+        if let Some(button_name) = name.strip_prefix("Pad:"){
+            return Button::from_string(button_name).map(InputBinding::GamepadButton);
+        }
+        if let Some(axis_name) = name.strip_prefix("PadAxis:"){
+            return Axis::from_string(axis_name).map(InputBinding::GamepadAxis);
+        }
+
+        match name.strip_prefix("Mouse:"){
+            Some(button_name) => {
+                let button = match button_name{
+                    "Left" => MouseButton::Left,
+                    "Middle" => MouseButton::Middle,
+                    "Right" => MouseButton::Right,
+                    "X1" => MouseButton::X1,
+                    "X2" => MouseButton::X2,
+                    _ => return None,
+                };
+                Some(InputBinding::Mouse(button))
+            },
+            None => Keycode::from_name(name).map(InputBinding::Key),
+        }
+    }
+}
+
+// mappa azione -> binding, caricata e salvata su un file di testo del tipo:
+//   MoveUp = W | Up
+//   Fire = Mouse:Left
+// il separatore e' '|' perche' la virgola e' il nome SDL di un tasto
+pub struct InputBindings{
+    bindings:HashMap<InputAction, Vec<InputBinding>>,
+}
+
+// versione del formato di bindings.cfg: dalla 2 il file contiene anche i binding del gamepad
+const BINDINGS_FILE_VERSION:u32 = 2;
+
+impl InputBindings{
+    pub fn new() -> Self{
+        let mut input_bindings = InputBindings{
+            bindings:HashMap::new(),
+        };
+        for action in InputAction::ALL{
+            input_bindings.reset_to_default(action);
+        }
+        input_bindings
+    }
+
+    pub fn default_bindings(action:InputAction) -> Vec<InputBinding>{
+        match action{
+            InputAction::MoveUp => vec![InputBinding::Key(Keycode::W)],
+            InputAction::MoveDown => vec![InputBinding::Key(Keycode::S)],
+            InputAction::MoveLeft => vec![InputBinding::Key(Keycode::A)],
+            InputAction::MoveRight => vec![InputBinding::Key(Keycode::D)],
+            InputAction::Fire => vec![InputBinding::Mouse(MouseButton::Left), InputBinding::GamepadAxis(Axis::TriggerRight)],
+            InputAction::ToggleAim => vec![InputBinding::Key(Keycode::F), InputBinding::GamepadButton(Button::LeftShoulder)],
+            InputAction::Dash => vec![InputBinding::Key(Keycode::Space), InputBinding::GamepadButton(Button::A)],
+            InputAction::Pause => vec![InputBinding::Key(Keycode::P), InputBinding::GamepadButton(Button::Start)],
+            InputAction::SwitchWeapon => vec![InputBinding::Key(Keycode::Q), InputBinding::GamepadButton(Button::Y)],
+            InputAction::ToggleDebug => vec![InputBinding::Key(Keycode::F3)],
+        }
+    }
+
+    // carica i binding dal file, le azioni mancanti o non valide mantengono quelli di default.
+    // Una lista vuota salvata nel file e' voluta (azione senza tasti) e viene rispettata
+    pub fn load(path:&str) -> Result<Self, String>{
+        let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut input_bindings = InputBindings::new();
+        let mut version = 1; // i file senza riga "version" sono stati salvati prima del supporto ai gamepad
+        let mut loaded_bindings = Vec::new();
+
+        for (line_number, line) in content.lines().enumerate(){
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#'){
+                continue; // righe vuote e commenti
+            }
+
+            let (action_name, bindings_names) = match line.split_once('='){
+                Some(split) => split,
+                None => {
+                    println!("{}:{} riga non valida: {}", path, line_number + 1, line);
+                    continue;
+                }
+            };
+
+            if action_name.trim() == "version"{
+                match bindings_names.trim().parse(){
+                    Ok(file_version) => version = file_version,
+                    Err(_) => println!("{}:{} versione non valida: {}", path, line_number + 1, bindings_names.trim()),
+                }
+                continue;
+            }
+
+            let action = match InputAction::from_name(action_name.trim()){
+                Some(action) => action,
+                None => {
+                    println!("{}:{} azione sconosciuta: {}", path, line_number + 1, action_name.trim());
+                    continue;
+                }
+            };
+
+            let mut bindings = Vec::new();
+            for binding_name in bindings_names.split('|').map(str::trim).filter(|name| !name.is_empty()){
+                match InputBinding::from_name(binding_name){
+                    Some(binding) => bindings.push(binding),
+                    None => {
+                        // file salvati con il vecchio separatore (virgola)
+                        for old_name in binding_name.split(',').map(str::trim).filter(|name| !name.is_empty()){
+                            match InputBinding::from_name(old_name){
+                                Some(binding) => bindings.push(binding),
+                                None => println!("{}:{} tasto sconosciuto: {}", path, line_number + 1, old_name),
+                            }
+                        }
+                    },
+                }
+            }
+            loaded_bindings.push((action, bindings));
+        }
+
+        // un file salvato prima del supporto ai gamepad non ha nessun binding del gamepad:
+        // in quel caso si aggiungono i bottoni di default, altrimenti il file viene usato cosi' com'e'
+        let predates_gamepad = version < BINDINGS_FILE_VERSION
+            && !loaded_bindings.iter().any(|(_, bindings)| bindings.iter().any(|binding| binding.is_gamepad()));
+        for (action, mut bindings) in loaded_bindings{
+            if predates_gamepad{
+                bindings.extend(InputBindings::default_bindings(action).into_iter().filter(|binding| binding.is_gamepad()));
+            }
+            input_bindings.bindings.insert(action, bindings);
+        }
+
+        Ok(input_bindings)
+    }
+
+    pub fn save(&self, path:&str) -> Result<(), String>{
+        let mut content = String::from("# azione = tasto | tasto | ... (nomi dei tasti SDL, Mouse:Left/Middle/Right/X1/X2, Pad:<bottone>, PadAxis:<asse>)\n");
+        content += &format!("version = {}\n", BINDINGS_FILE_VERSION);
+        for action in InputAction::ALL{
+            content += &format!("{} = {}\n", action.get_name(), self.get_bindings_text(action));
+        }
+
+        std::fs::write(path, content).map_err(|error| error.to_string())
+    }
+
+    pub fn get_bindings(&self, action:InputAction) -> &[InputBinding]{
+        self.bindings.get(&action).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn get_bindings_text(&self, action:InputAction) -> String{
+        self.get_bindings(action).iter().map(|binding| binding.get_name()).collect::<Vec<String>>().join(" | ")
+    }
+
+    // il nuovo binding sostituisce quelli attuali dell'azione dello stesso tipo (tastiera/mouse o gamepad),
+    // cosi' cambiando il tasto non si perde il bottone del gamepad e viceversa
+    pub fn rebind(&mut self, action:InputAction, binding:InputBinding){
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|current| current.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+
+    // il nuovo binding si aggiunge a quelli attuali dell'azione
+    pub fn add_binding(&mut self, action:InputAction, binding:InputBinding){
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding){
+            bindings.push(binding);
+        }
+    }
+
+    pub fn reset_to_default(&mut self, action:InputAction){
+        self.bindings.insert(action, InputBindings::default_bindings(action));
+    }
+}
+
+// valori degli assi del gamepad (i16) normalizzati tra -1.0 e 1.0
+const GAMEPAD_AXIS_MAX:f32 = 32767.0;
+// un asse usato come bottone (es. grilletto) e' premuto oltre questa soglia
+const GAMEPAD_AXIS_PRESS_THRESHOLD:f32 = 0.5;
+
+// sorgente della mira: l'ultima usata tra mouse e stick destro
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AimSource{
+    Mouse,
+    Gamepad,
+}
+
+// stato dell'input letto ad ogni frame dall'EventPump: quali tasti e bottoni del mouse sono tenuti premuti,
+// e quali sono stati premuti proprio in questo frame. Non dipende dagli eventi KeyDown/KeyUp,
+// quindi la ripetizione automatica dei tasti del sistema operativo non ha effetto
+pub struct InputState{
+    pub bindings:InputBindings,
+    held_keys:HashSet<Keycode>,
+    previous_held_keys:HashSet<Keycode>,
+    held_mouse_buttons:HashSet<MouseButton>,
+    previous_held_mouse_buttons:HashSet<MouseButton>,
+
+    // gamepad (se il sottosistema SDL e' disponibile), ne viene usato uno alla volta
+    gamepad_subsystem:Option<GameControllerSubsystem>,
+    gamepad:Option<GameController>,
+    held_gamepad_bindings:HashSet<InputBinding>, // solo binding GamepadButton / GamepadAxis
+    previous_held_gamepad_bindings:HashSet<InputBinding>,
+    left_stick:FPoint,
+    right_stick:FPoint,
+    pub stick_dead_zone:f32, // tra 0.0 e 1.0, sotto questo valore lo stick e' considerato fermo
+    aim_source:AimSource,
+    last_stick_aim:FPoint, // ultima direzione valida dello stick destro (si mantiene quando lo stick torna al centro)
+}
+
+impl InputState{
+    pub fn new(bindings:InputBindings, gamepad_subsystem:Option<GameControllerSubsystem>) -> Self{
+        let mut input_state = InputState{
+            bindings,
+            held_keys:HashSet::new(),
+            previous_held_keys:HashSet::new(),
+            held_mouse_buttons:HashSet::new(),
+            previous_held_mouse_buttons:HashSet::new(),
+            gamepad_subsystem,
+            gamepad:None,
+            held_gamepad_bindings:HashSet::new(),
+            previous_held_gamepad_bindings:HashSet::new(),
+            left_stick:FPoint::new(0.0, 0.0),
+            right_stick:FPoint::new(0.0, 0.0),
+            stick_dead_zone:0.25,
+            aim_source:AimSource::Mouse,
+            last_stick_aim:FPoint::new(1.0, 0.0),
+        };
+        input_state.open_first_gamepad();
+        input_state
+    }
+
+    // apre il primo gamepad collegato (se non ce n'e' gia' uno aperto)
+    fn open_first_gamepad(&mut self){
+        let gamepad_subsystem = match &self.gamepad_subsystem{
+            Some(gamepad_subsystem) if self.gamepad.is_none() => gamepad_subsystem,
+            _ => return,
+        };
+
+        let joysticks = gamepad_subsystem.num_joysticks().unwrap_or(0);
+        for index in 0..joysticks{
+            if gamepad_subsystem.is_game_controller(index){
+                match gamepad_subsystem.open(index){
+                    Ok(gamepad) => {
+                        println!("Gamepad collegato: {}", gamepad.name());
+                        self.gamepad = Some(gamepad);
+                        return;
+                    },
+                    Err(error) => println!("Errore apertura gamepad: {}", error),
+                }
+            }
+        }
+    }
+
+    // eventi per hot-plug del gamepad e per capire quale sorgente di mira e' stata usata per ultima
+    pub fn manage_event(&mut self, event:&Event){
+        match event{
+            Event::ControllerDeviceAdded { .. } => {
+                self.open_first_gamepad();
+            },
+            Event::ControllerDeviceRemoved { which, .. } if self.gamepad.as_ref().map(|gamepad| gamepad.instance_id()) == Some(*which) => {
+                println!("Gamepad scollegato");
+                self.gamepad = None;
+                self.aim_source = AimSource::Mouse;
+                self.open_first_gamepad(); // eventuale altro gamepad ancora collegato
+            },
+            Event::MouseMotion { .. } => {
+                self.aim_source = AimSource::Mouse;
+            },
+            _ => {}
+        }
+    }
+
+    // dead zone radiale: sotto la soglia lo stick vale 0, sopra si riscala in modo che il valore parta da 0
+    fn apply_dead_zone(stick:FPoint, dead_zone:f32) -> FPoint{
+        let magnitude = Utils::point_magnitude(stick);
+        if magnitude <= dead_zone{
+            return FPoint::new(0.0, 0.0);
+        }
+
+        let rescaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+        stick / magnitude * rescaled
+    }
+
+    fn read_gamepad(&mut self){
+        self.previous_held_gamepad_bindings = std::mem::take(&mut self.held_gamepad_bindings);
+
+        let gamepad = match &self.gamepad{
+            Some(gamepad) => gamepad,
+            None => {
+                self.left_stick = FPoint::new(0.0, 0.0);
+                self.right_stick = FPoint::new(0.0, 0.0);
+                return;
+            }
+        };
+
+        let axis = |axis:Axis| gamepad.axis(axis) as f32 / GAMEPAD_AXIS_MAX;
+        self.left_stick = InputState::apply_dead_zone(FPoint::new(axis(Axis::LeftX), axis(Axis::LeftY)), self.stick_dead_zone);
+        self.right_stick = InputState::apply_dead_zone(FPoint::new(axis(Axis::RightX), axis(Axis::RightY)), self.stick_dead_zone);
+
+        // si controllano solo i binding del gamepad effettivamente assegnati a qualche azione
+        for action in InputAction::ALL{
+            for binding in self.bindings.get_bindings(action){
+                let held = match binding{
+                    InputBinding::GamepadButton(button) => gamepad.button(*button),
+                    InputBinding::GamepadAxis(binding_axis) => axis(*binding_axis) > GAMEPAD_AXIS_PRESS_THRESHOLD,
+                    _ => false,
+                };
+                if held{
+                    self.held_gamepad_bindings.insert(*binding);
+                }
+            }
+        }
+
+        // lo stick destro mosso oltre la dead zone diventa la sorgente di mira
+        if self.right_stick != FPoint::new(0.0, 0.0){
+            self.aim_source = AimSource::Gamepad;
+            self.last_stick_aim = Utils::point_normalized(self.right_stick);
+        }
+    }
+
+    pub fn get_move_stick(&self) -> FPoint{
+        self.left_stick
+    }
+
+    // direzione di mira del gamepad, None se l'ultima sorgente usata e' il mouse
+    pub fn get_gamepad_aim(&self) -> Option<FPoint>{
+        match self.aim_source{
+            AimSource::Gamepad => Some(self.last_stick_aim),
+            AimSource::Mouse => None,
+        }
+    }
+
+    // da chiamare una volta per frame, dopo aver consumato gli eventi (poll_iter aggiorna lo stato interno di SDL)
+    pub fn update(&mut self, event_pump:&EventPump){
+        self.previous_held_keys = std::mem::take(&mut self.held_keys);
+        self.previous_held_mouse_buttons = std::mem::take(&mut self.held_mouse_buttons);
+
+        self.held_keys = event_pump.keyboard_state().pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .collect();
+        self.held_mouse_buttons = event_pump.mouse_state().pressed_mouse_buttons().collect();
+
+        self.read_gamepad();
+    }
+
+    pub fn is_key_held(&self, key:Keycode) -> bool{
+        self.held_keys.contains(&key)
+    }
+
+    // true solo nel frame in cui il tasto passa da rilasciato a premuto
+    pub fn is_key_just_pressed(&self, key:Keycode) -> bool{
+        self.held_keys.contains(&key) && !self.previous_held_keys.contains(&key)
+    }
+
+    pub fn is_mouse_button_held(&self, button:MouseButton) -> bool{
+        self.held_mouse_buttons.contains(&button)
+    }
+
+    pub fn is_mouse_button_just_pressed(&self, button:MouseButton) -> bool{
+        self.held_mouse_buttons.contains(&button) && !self.previous_held_mouse_buttons.contains(&button)
+    }
+
+    // un'azione e' attiva se almeno uno dei suoi binding e' premuto
+    pub fn is_action_held(&self, action:InputAction) -> bool{
+        self.bindings.get_bindings(action).iter().any(|binding| match binding{
+            InputBinding::Key(key) => self.is_key_held(*key),
+            InputBinding::Mouse(button) => self.is_mouse_button_held(*button),
+            _ => self.held_gamepad_bindings.contains(binding),
+        })
+    }
+
+    pub fn is_action_just_pressed(&self, action:InputAction) -> bool{
+        self.bindings.get_bindings(action).iter().any(|binding| match binding{
+            InputBinding::Key(key) => self.is_key_just_pressed(*key),
+            InputBinding::Mouse(button) => self.is_mouse_button_just_pressed(*button),
+            _ => self.held_gamepad_bindings.contains(binding) && !self.previous_held_gamepad_bindings.contains(binding),
+        })
+    }
+}
+
+// ------------- POOL -------------
+
+// statistiche di un pool, mostrate nell'overlay di debug
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStats{
+    pub active:usize,
+    pub capacity:usize,
+    pub allocations:usize, // istanze create (quelle iniziali + quelle aggiunte quando il pool cresce)
+    pub reuses:usize, // acquire serviti riciclando un'istanza gia' usata e rilasciata
+    pub peak_active:usize,
+}
+
+// pool generico di oggetti riutilizzabili: le istanze vengono create una volta e poi riciclate, chi fa acquire
+// deve reinizializzare l'istanza (es. Bullet::reset). Se il pool e' pieno cresce solo se growable, altrimenti
+// acquire ritorna None (come per le particelle, dove si puo' perdere qualche effetto)
+pub struct Pool<T>{
+    items:Vec<T>,
+    active:Vec<bool>,
+    used:Vec<bool>, // slot gia' usato almeno una volta (solo questi contano come riutilizzi)
+    free_slots:Vec<usize>,
+    active_count:usize,
+    growable:bool,
+    create:fn() -> T, // costruttore delle nuove istanze
+    allocations:usize,
+    reuses:usize,
+    peak_active:usize,
+}
+
+impl<T> Pool<T>{
+    pub fn new(capacity:usize, growable:bool, create:fn() -> T) -> Self{
+        Pool{
+            items:(0..capacity).map(|_| create()).collect(),
+            active:vec![false; capacity],
+            used:vec![false; capacity],
+            free_slots:(0..capacity).rev().collect(),
+            active_count:0,
+            growable,
+            create,
+            allocations:capacity,
+            reuses:0,
+            peak_active:0,
+        }
+    }
+
+    pub fn acquire(&mut self) -> Option<&mut T>{
+        let slot = match self.free_slots.pop(){
+            Some(slot) => {
+                if self.used[slot]{
+                    self.reuses += 1; // il primo uso delle istanze create all'inizio non e' un riutilizzo
+                }
+                slot
+            },
+            None if self.growable => {
+                self.items.push((self.create)());
+                self.active.push(false);
+                self.used.push(false);
+                self.allocations += 1;
+                self.items.len() - 1
+            },
+            None => return None, // pool pieno
+        };
+
+        self.active[slot] = true;
+        self.used[slot] = true;
+        self.active_count += 1;
+        self.peak_active = self.peak_active.max(self.active_count);
+        Some(&mut self.items[slot])
+    }
+
+    pub fn release(&mut self, slot:usize){
+        if self.active.get(slot).copied().unwrap_or(false){
+            self.active[slot] = false;
+            self.active_count -= 1;
+            self.free_slots.push(slot);
+        }
+    }
+
+    // come Vec::retain: le istanze per cui keep ritorna false tornano disponibili (senza essere droppate)
+    pub fn retain(&mut self, mut keep:impl FnMut(&mut T) -> bool){
+        for slot in 0..self.items.len(){
+            if self.active[slot] && !keep(&mut self.items[slot]){
+                self.release(slot);
+            }
+        }
+    }
+
+    pub fn is_full(&self) -> bool{
+        self.free_slots.is_empty() && !self.growable
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T>{
+        self.items.iter().zip(self.active.iter()).filter(|(_, active)| **active).map(|(item, _)| item)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T>{
+        self.items.iter_mut().zip(self.active.iter()).filter(|(_, active)| **active).map(|(item, _)| item)
+    }
+
+    // come iter ma con lo slot di ogni istanza, da passare a release
+    pub fn iter_slots(&self) -> impl Iterator<Item = (usize, &T)>{
+        self.items.iter().enumerate().filter(|(slot, _)| self.active[*slot])
+    }
+
+    pub fn get_stats(&self) -> PoolStats{
+        PoolStats{
+            active:self.active_count,
+            capacity:self.items.len(),
+            allocations:self.allocations,
+            reuses:self.reuses,
+            peak_active:self.peak_active,
+        }
+    }
+}
+
+// allocatore globale (registrato nel main) che conta le allocazioni nello heap, per l'overlay di debug e il benchmark dei pool.
+// Solo con la feature alloc-counter, in modo che le build normali non paghino un'operazione atomica per ogni allocazione
+#[cfg(feature = "alloc-counter")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "alloc-counter")]
+static ALLOCATION_COUNT:AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "alloc-counter")]
+unsafe impl GlobalAlloc for CountingAllocator{
+    unsafe fn alloc(&self, layout:Layout) -> *mut u8{
+        ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr:*mut u8, layout:Layout){
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr:*mut u8, layout:Layout, new_size:usize) -> *mut u8{
+        ALLOCATION_COUNT.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+// allocazioni fatte dall'avvio del programma, None se il conteggio non e' attivo (feature alloc-counter)
+#[cfg(feature = "alloc-counter")]
+pub fn allocation_count() -> Option<usize>{
+    Some(ALLOCATION_COUNT.load(Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "alloc-counter"))]
+pub fn allocation_count() -> Option<usize>{
+    None
+}
+
+// implementazione dei numeri del danno prima dei pool (coda con scarto del piu' vecchio), tenuta solo
+// come termine di paragone per il benchmark. Il rendering non cambia, quindi c'e' solo spawn / update
+struct UnpooledDamageNumbers{
+    numbers:VecDeque<DamageNumber>,
+    capacity:usize,
+}
+
+impl UnpooledDamageNumbers{
+    fn new(capacity:usize) -> Self{
+        UnpooledDamageNumbers{
+            numbers:VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn spawn(&mut self, damage:&DamageInfo, world_position:FPoint){
+        if damage.amount <= 0 || self.capacity == 0{
+            return;
+        }
+
+        if self.numbers.len() >= self.capacity{
+            self.numbers.pop_front();
+        }
+
+        let horizontal_speed = rand::thread_rng().gen_range(-25.0..25.0);
+        self.numbers.push_back(DamageNumber{
+            text: if damage.critical { format!("{}!", damage.amount) } else { damage.amount.to_string() },
+            position:world_position,
+            velocity:FPoint::new(horizontal_speed, -DAMAGE_NUMBER_RISE_SPEED),
+            color:damage.damage_type.get_color(),
+            scale: if damage.critical { DAMAGE_NUMBER_CRIT_SCALE } else { 1.0 },
+            age:0.0,
+        });
+    }
+
+    fn update(&mut self, deltatime:f32){
+        for number in self.numbers.iter_mut(){
+            number.age += deltatime;
+            number.position += number.velocity * deltatime;
+            number.velocity *= (1.0 - 2.0 * deltatime).max(0.0);
+        }
+
+        while self.numbers.front().is_some_and(|number| number.age >= DAMAGE_NUMBER_LIFETIME){
+            self.numbers.pop_front();
+        }
+    }
+}
+
+// confronta le allocazioni di una raffica di proiettili e numeri del danno con e senza pool
+// (cargo run --release --features alloc-counter -- --bench-pools).
+// Non serve SDL: si simulano solo update e rimozione, senza rendering
+pub fn run_pool_benchmark(frames:u32, shots_per_frame:u32){
+    if allocation_count().is_none(){
+        println!("Benchmark pool non disponibile: compilare con --features alloc-counter");
+        return;
+    }
+    let allocation_count = || allocation_count().unwrap_or(0);
+    let deltatime = 1.0 / 60.0;
+    let utils = Utils::new();
+    let damage = DamageInfo::new(10, DamageType::Kinetic, EntityType::Player);
+
+    // prima: un Box per ogni colpo nella lista dei gameobjects, rimosso con retain
+    let mut gameobjects:Vec<Box<dyn GameObject>> = Vec::new();
+    let start = allocation_count();
+    for frame in 0..frames{
+        for shot in 0..shots_per_frame{
+            let direction = Utils::rotate_point(FPoint::new(1.0, 0.0), (frame * shots_per_frame + shot) as f64 * 7.0);
+            gameobjects.push(Box::new(Bullet::new(direction, EntityType::Player, 200.0, FPoint::new(0.0, 0.0))));
+        }
+        for game_object in gameobjects.iter_mut(){
+            game_object.update(deltatime, &utils);
+        }
+        gameobjects.retain(|game_object| !game_object.is_destroyed());
+    }
+    let bullets_before = allocation_count() - start;
+
+    // dopo: i bullet vengono riciclati dal pool
+    let mut bullets:Pool<Bullet> = Pool::new(256, true, || Bullet::new(FPoint::new(1.0, 0.0), EntityType::Player, 0.0, FPoint::new(0.0, 0.0)));
+    let start = allocation_count();
+    for frame in 0..frames{
+        for shot in 0..shots_per_frame{
+            let direction = Utils::rotate_point(FPoint::new(1.0, 0.0), (frame * shots_per_frame + shot) as f64 * 7.0);
+            if let Some(bullet) = bullets.acquire(){
+                bullet.reset(direction, EntityType::Player, 200.0, FPoint::new(0.0, 0.0));
+            }
+        }
+        for bullet in bullets.iter_mut(){
+            bullet.update(deltatime, &utils);
+        }
+        bullets.retain(|bullet| !bullet.is_destroyed());
+    }
+    let bullets_after = allocation_count() - start;
+
+    // numeri del danno: prima la coda di DamageNumber senza pool (un testo nuovo per ogni colpo), dopo i testi riutilizzati dal pool
+    let mut unpooled_numbers = UnpooledDamageNumbers::new(256);
+    let start = allocation_count();
+    for _ in 0..frames * shots_per_frame{
+        unpooled_numbers.spawn(&damage, FPoint::new(0.0, 0.0));
+        unpooled_numbers.update(deltatime / shots_per_frame.max(1) as f32);
+    }
+    let numbers_before = allocation_count() - start;
+
+    let mut damage_numbers = DamageNumbers::new(256, ("hud", 20));
+    let start = allocation_count();
+    for _ in 0..frames * shots_per_frame{
+        damage_numbers.spawn(&damage, FPoint::new(0.0, 0.0));
+        damage_numbers.update(deltatime / shots_per_frame.max(1) as f32);
+    }
+    let numbers_after = allocation_count() - start;
+
+    println!("Benchmark pool: {} frame, {} colpi per frame", frames, shots_per_frame);
+    println!("Bullets        : {} allocazioni senza pool, {} con pool ({:?})", bullets_before, bullets_after, bullets.get_stats());
+    println!("Damage numbers : {} allocazioni senza pool, {} con pool ({:?})", numbers_before, numbers_after, damage_numbers.get_pool_stats());
+}
+
+// ------------- PARTICELLE -------------
+
+// regione della texture "particle" (cerchio sfumato generato in ParticleSystem::create_texture)
+const PARTICLE_TEXTURE_SIZE:u32 = 16;
+
+// parametri con cui vengono generate le particelle di un'emissione. I range (min, max) vengono campionati
+// per ogni particella
+#[derive(Clone, Copy)]
+pub struct ParticleEmitterConfig{
+    pub count:(u32, u32),
+    pub speed:(f32, f32), // pixel al secondo
+    pub spread:f32, // gradi attorno alla direzione di emissione (360 -> tutte le direzioni)
+    pub lifetime:(f32, f32), // secondi
+    pub drag:f32, // rallentamento al secondo (0.0 -> nessun attrito)
+    pub color_start:Color,
+    pub color_end:Color, // colore e alpha vengono interpolati lungo la vita della particella
+    pub size_start:(f32, f32),
+    pub size_end:f32, // moltiplicatore della dimensione iniziale a fine vita
+    pub texture_region:Option<Rect>, // None -> quadrato pieno
+    pub additive:bool, // blending additivo (fiamme, flash), altrimenti alpha blending (sangue, fumo)
+}
+
+impl ParticleEmitterConfig{
+    pub fn muzzle_flash() -> Self{
+        ParticleEmitterConfig{
+            count:(4, 7),
+            speed:(60.0, 160.0),
+            spread:30.0,
+            lifetime:(0.05, 0.12),
+            drag:8.0,
+            color_start:Color::RGBA(255, 230, 140, 255),
+            color_end:Color::RGBA(255, 120, 20, 0),
+            size_start:(5.0, 9.0),
+            size_end:0.3,
+            texture_region:Some(Rect::new(0, 0, PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE)),
+            additive:true,
+        }
+    }
+
+    pub fn impact() -> Self{
+        ParticleEmitterConfig{
+            count:(5, 8),
+            speed:(40.0, 120.0),
+            spread:120.0,
+            lifetime:(0.1, 0.25),
+            drag:6.0,
+            color_start:Color::RGBA(255, 255, 200, 255),
+            color_end:Color::RGBA(255, 160, 40, 0),
+            size_start:(2.0, 4.0),
+            size_end:0.5,
+            texture_region:None,
+            additive:true,
+        }
+    }
+
+    pub fn blood() -> Self{
+        ParticleEmitterConfig{
+            count:(6, 10),
+            speed:(30.0, 90.0),
+            spread:70.0,
+            lifetime:(0.3, 0.6),
+            drag:5.0,
+            color_start:Color::RGBA(170, 0, 0, 255),
+            color_end:Color::RGBA(90, 0, 0, 0),
+            size_start:(3.0, 6.0),
+            size_end:0.8,
+            texture_region:Some(Rect::new(0, 0, PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE)),
+            additive:false,
+        }
+    }
+
+    pub fn explosion() -> Self{
+        ParticleEmitterConfig{
+            count:(20, 30),
+            speed:(40.0, 200.0),
+            spread:360.0,
+            lifetime:(0.3, 0.7),
+            drag:4.0,
+            color_start:Color::RGBA(255, 200, 80, 255),
+            color_end:Color::RGBA(120, 30, 10, 0),
+            size_start:(8.0, 16.0),
+            size_end:1.8,
+            texture_region:Some(Rect::new(0, 0, PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE)),
+            additive:true,
+        }
+    }
+}
+
+struct Particle{
+    position:FPoint,
+    velocity:FPoint,
+    drag:f32,
+    age:f32,
+    lifetime:f32,
+    color_start:Color,
+    color_end:Color,
+    size_start:f32,
+    size_end:f32,
+    texture_region:Option<Rect>,
+    additive:bool,
+}
+
+impl Particle{
+    // istanza vuota con cui si riempie il pool, viene sovrascritta in emit
+    fn empty() -> Self{
+        Particle{
+            position:FPoint::new(0.0, 0.0),
+            velocity:FPoint::new(0.0, 0.0),
+            drag:0.0,
+            age:0.0,
+            lifetime:0.0,
+            color_start:Color::RGBA(0, 0, 0, 0),
+            color_end:Color::RGBA(0, 0, 0, 0),
+            size_start:0.0,
+            size_end:0.0,
+            texture_region:None,
+            additive:false,
+        }
+    }
+}
+
+// pool di particelle a capacita' fissa: gli slot vengono allocati una sola volta e riutilizzati,
+// quando il pool e' pieno le nuove particelle vengono scartate
+pub struct ParticleSystem{
+    particles:Pool<Particle>,
+}
+
+impl ParticleSystem{
+    pub fn new(capacity:usize) -> Self{
+        ParticleSystem{
+            particles:Pool::new(capacity, false, Particle::empty),
+        }
+    }
+
+    pub fn get_pool_stats(&self) -> PoolStats{
+        self.particles.get_stats()
+    }
+
+    // genera la texture "particle": un cerchio bianco che sfuma verso i bordi. Essendo bianca si colora
+    // con set_color_mod al momento del rendering
+    pub fn create_texture<'l>(resource_manager:&ResourceManager<'l>) -> Result<Texture<'l>, String>{
+        let mut surface = Surface::new(PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE, PixelFormatEnum::RGBA32)?;
+        let pitch = surface.pitch() as usize;
+        let radius = PARTICLE_TEXTURE_SIZE as f32 / 2.0;
+
+        surface.with_lock_mut(|pixels:&mut [u8]| {
+            for y in 0..PARTICLE_TEXTURE_SIZE as usize{
+                for x in 0..PARTICLE_TEXTURE_SIZE as usize{
+                    let distance = Utils::calculate_point_distance(FPoint::new(x as f32 + 0.5, y as f32 + 0.5), FPoint::new(radius, radius));
+                    let alpha = (1.0 - distance / radius).clamp(0.0, 1.0);
+                    let index = y * pitch + x * 4;
+                    pixels[index..index + 4].copy_from_slice(&[255, 255, 255, (alpha * alpha * 255.0) as u8]);
+                }
+            }
+        });
+
+        resource_manager.get_texture_from_surface(surface)
+    }
+
+    pub fn emit(&mut self, config:&ParticleEmitterConfig, position:FPoint, direction:FPoint){
+        let mut rng = rand::thread_rng();
+        let base_angle = direction.y.atan2(direction.x).to_degrees();
+        let count = rng.gen_range(config.count.0..=config.count.1);
+
+        for _ in 0..count{
+            let particle = match self.particles.acquire(){
+                Some(particle) => particle,
+                None => return, // pool pieno
+            };
+
+            let angle = (base_angle + rng.gen_range(-config.spread / 2.0..=config.spread / 2.0)).to_radians();
+            let speed = rng.gen_range(config.speed.0..=config.speed.1);
+
+            *particle = Particle{
+                position,
+                velocity:FPoint::new(angle.cos(), angle.sin()) * speed,
+                drag:config.drag,
+                age:0.0,
+                lifetime:rng.gen_range(config.lifetime.0..=config.lifetime.1),
+                color_start:config.color_start,
+                color_end:config.color_end,
+                size_start:rng.gen_range(config.size_start.0..=config.size_start.1),
+                size_end:config.size_end,
+                texture_region:config.texture_region,
+                additive:config.additive,
+            };
+        }
+    }
+
+    pub fn update(&mut self, deltatime:f32){
+        self.particles.retain(|particle| {
+            particle.age += deltatime;
+
+            if particle.age >= particle.lifetime{
+                return false; // slot di nuovo disponibile
+            }
+
+            particle.velocity *= (1.0 - particle.drag * deltatime).max(0.0);
+            particle.position += particle.velocity * deltatime;
+            true
+        });
+    }
+
+    pub fn draw(&self, canvas:&mut WindowCanvas, mut texture:Option<&mut Texture>, game_utils:&Utils) -> Result<(), String>{
+        for particle in self.particles.iter(){
+            let t = particle.age / particle.lifetime; // tra 0.0 e 1.0
+            let color = Utils::lerp_color(particle.color_start, particle.color_end, t);
+            let size = particle.size_start * (1.0 + (particle.size_end - 1.0) * t) * game_utils.main_camera_zoom;
+
+            let screen_position = game_utils.world_to_screen(particle.position);
+            let target = Rect::new(
+                (screen_position.x - size / 2.0).round() as i32,
+                (screen_position.y - size / 2.0).round() as i32,
+                size.round().max(1.0) as u32,
+                size.round().max(1.0) as u32,
+            );
+            let blend_mode = if particle.additive { BlendMode::Add } else { BlendMode::Blend };
+
+            match (particle.texture_region, texture.as_deref_mut()){
+                (Some(region), Some(texture)) => {
+                    texture.set_blend_mode(blend_mode);
+                    texture.set_color_mod(color.r, color.g, color.b);
+                    texture.set_alpha_mod(color.a);
+                    canvas.copy(texture, region, target)?;
+                },
+                _ => {
+                    canvas.set_blend_mode(blend_mode);
+                    canvas.set_draw_color(color);
+                    canvas.fill_rect(target)?;
+                },
+            }
+        }
+        canvas.set_blend_mode(BlendMode::None);
+        // la texture e' condivisa (es. sangue delle decal), si ripristina come per color/alpha mod
+        if let Some(texture) = texture{
+            texture.set_blend_mode(BlendMode::Blend);
+            texture.set_color_mod(255, 255, 255);
+            texture.set_alpha_mod(255);
+        }
+
+        Ok(())
+    }
+}
+
+// ------------- BOSS -------------
+
+const BOSS_PHASE_THRESHOLDS:[f32; 2] = [0.66, 0.33]; // frazioni di vita sotto le quali si passa alla fase successiva
+const BOSS_ATTACK_COOLDOWN:f32 = 3.0; // secondi tra un attacco e l'altro in fase 1, si riduce nelle fasi successive
+const BOSS_CHARGE_WINDUP:f32 = 0.7; // secondi fermo prima della carica (il giocatore ha il tempo di spostarsi)
+const BOSS_CHARGE_TIME:f32 = 0.8;
+const BOSS_CHARGE_SPEED:f32 = 320.0;
+const BOSS_BURST_BULLETS:u32 = 16;
+const BOSS_BULLET_SPEED:f32 = 140.0;
+const BOSS_ARMOR:i32 = 2;
+const BOSS_PHASE_SHIELD:i32 = 60; // punti scudo all'inizio di ogni fase dopo la prima
+const BOSS_BULLET_LIFE:i32 = 450; // distanza percorsa prima di sparire, piu' dei bullet normali
+const BOSS_SUMMON_COUNT:u32 = 3;
+const BOSS_COLLISION_RADIUS:f32 = 28.0;
+const BOSS_SCALE:f32 = 2.0; // lo sprite e' quello dei nemici ingrandito
+
+// attacchi del boss: ogni fase ne sblocca uno nuovo, poi il boss li alterna tutti
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BossAttack{
+    Charge, // fase 1
+    RadialBurst, // fase 2
+    Summon, // fase 3
+}
+
+impl BossAttack{
+    pub const ALL:[BossAttack; 3] = [BossAttack::Charge, BossAttack::RadialBurst, BossAttack::Summon];
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum BossState{
+    Chasing,
+    ChargeWindup(f32, FPoint), // (tempo rimasto, direzione della carica)
+    Charging(f32, FPoint),
+}
+
+pub struct Boss{
+    boss_entity:Entity,
+    health:i32,
+    current_health:i32,
+    phase:u32, // 1, 2 o 3
+    state:BossState,
+    attack_cooldown:f32,
+    next_attack:usize, // indice in BossAttack::ALL
+    pending_spawns:Vec<Box<dyn GameObject>>, // nemici evocati dal boss, aggiunti da Game ai gameobjects
+    pending_bullets:Vec<(FPoint, f32, FPoint)>, // (direzione, velocita', posizione) dei proiettili da prendere dal pool
+    minion_id:u32,
+    pub contact_damage:i32,
+    pub score_value:i32,
+    pub resistances:Resistances,
+    pub damage_pipeline:DamagePipeline,
+    killed_by:Option<EntityType>,
+    last_hit_direction:FPoint,
+}
+
+impl Boss{
+    pub fn new(name:&str, health:i32, speed:f32, position:FPoint) -> Self{
+        let mut boss_entity = Entity::with_speed(name, speed, EntityType::Enemy);
+        boss_entity.set_sprite(51, 43);
+        boss_entity.entity_sprite.current_frame = 2; // altra riga dello spritesheet, diverso dai nemici normali
+        boss_entity.set_position(position);
+
+        let mut damage_pipeline = DamagePipeline::new();
+        damage_pipeline.add_modifier(Box::new(ArmorModifier::new(BOSS_ARMOR)));
+
+        Boss{
+            boss_entity,
+            health,
+            current_health:health,
+            phase:1,
+            state:BossState::Chasing,
+            attack_cooldown:BOSS_ATTACK_COOLDOWN,
+            next_attack:0,
+            pending_spawns:Vec::new(),
+            pending_bullets:Vec::new(),
+            minion_id:0,
+            contact_damage:20,
+            score_value:500,
+            resistances:Resistances::new(0.1, 0.0, 0.0),
+            damage_pipeline,
+            killed_by:None,
+            last_hit_direction:FPoint::new(0.0, 0.0),
+        }
+    }
+
+    pub fn get_phase(&self) -> u32{
+        self.phase
+    }
+
+    // oggetti creati dal boss dall'ultima chiamata (come Player::take_recent_damage)
+    pub fn take_spawns(&mut self) -> Vec<Box<dyn GameObject>>{
+        std::mem::take(&mut self.pending_spawns)
+    }
+
+    // i proiettili sparati dall'ultima chiamata vengono presi dal pool di game
+    pub fn fire_bullets(&mut self, bullets:&mut Pool<Bullet>){
+        for (direction, speed, position) in self.pending_bullets.drain(..){
+            if let Some(bullet) = bullets.acquire(){
+                bullet.reset(direction, EntityType::Enemy, speed, position);
+                bullet.owner_id = Some(self.boss_entity.get_id());
+                bullet.damage_type = DamageType::Fire; // sfere di fuoco
+                bullet.bullet_life = BOSS_BULLET_LIFE;
+                bullet.crit_chance = 0.0;
+            }
+        }
+    }
+
+    pub fn get_death_info(&self) -> EnemyDeath{
+        EnemyDeath{
+            position:self.boss_entity.get_position(),
+            rotation:self.boss_entity.get_rotation(),
+            hit_direction:self.last_hit_direction,
+            archetype:EnemyArchetype::Brute,
+            sprite:Rect::new(0, 43 * self.boss_entity.entity_sprite.current_frame, 51, 43),
+            killed_by:self.killed_by,
+        }
+    }
+
+    // a differenza dei nemici normali il boss non si distrugge al contatto, colpisce di nuovo quando finisce
+    // l'invulnerabilita' del player
+    pub fn damage_player<T>(&mut self, player:&mut T) where T : Damageable{
+        if player.is_invulnerable(){
+            return;
+        }
+
+        let player_position = player.get_entity().get_position();
+        if Utils::calculate_point_distance(self.boss_entity.position, player_position) < BOSS_COLLISION_RADIUS + 10.0{
+            let direction = Utils::point_normalized(player_position - self.boss_entity.position);
+            // la carica fa piu' danno e spinge di piu'
+            let charging = matches!(self.state, BossState::Charging(..));
+            let damage = if charging { self.contact_damage * 2 } else { self.contact_damage };
+            player.take_damage(&DamageInfo::new(damage, DamageType::Kinetic, EntityType::Enemy).with_direction(direction)
+                .with_source_id(Some(self.boss_entity.get_id())));
+            player.apply_knockback(direction, if charging { 600.0 } else { 350.0 });
+        }
+    }
+
+    fn update_phase(&mut self){
+        let fraction = self.get_health_fraction();
+        let phase = 1 + BOSS_PHASE_THRESHOLDS.iter().filter(|threshold| fraction < **threshold).count() as u32;
+
+        if phase > self.phase{
+            // il nuovo attacco viene usato subito, cosi' il cambio di fase si nota
+            self.phase = phase;
+            self.next_attack = (phase - 1) as usize;
+            self.attack_cooldown = 0.0;
+
+            // a ogni nuova fase il boss si protegge con uno scudo nuovo (quello vecchio, se rimasto, si perde)
+            self.damage_pipeline.clear_modifiers();
+            self.damage_pipeline.add_modifier(Box::new(ArmorModifier::new(BOSS_ARMOR)));
+            self.damage_pipeline.add_modifier(Box::new(ShieldModifier::new(BOSS_PHASE_SHIELD)));
+        }
+    }
+
+    fn start_attack(&mut self, attack:BossAttack, game_utils:&Utils){
+        match attack{
+            BossAttack::Charge => {
+                let direction = Utils::point_normalized(game_utils.get_player_position() - self.boss_entity.get_position());
+                self.state = BossState::ChargeWindup(BOSS_CHARGE_WINDUP, direction);
+            },
+            BossAttack::RadialBurst => {
+                // due anelli sfasati di mezzo intervallo
+                let step = 360.0 / BOSS_BURST_BULLETS as f64;
+                for ring in 0..2{
+                    for index in 0..BOSS_BURST_BULLETS{
+                        let angle = index as f64 * step + ring as f64 * step / 2.0;
+                        let direction = Utils::rotate_point(FPoint::new(1.0, 0.0), angle);
+                        self.pending_bullets.push((direction, BOSS_BULLET_SPEED * (1.0 - 0.25 * ring as f32),
+                            self.boss_entity.get_position() + direction * BOSS_COLLISION_RADIUS));
+                    }
+                }
+            },
+            BossAttack::Summon => {
+                for index in 0..BOSS_SUMMON_COUNT{
+                    let archetype = if index % 2 == 0 { EnemyArchetype::Grunt } else { EnemyArchetype::Runner };
+                    let mut minion = Enemy::with_archetype(format!("{}_minion_{}", self.boss_entity.get_name(), self.minion_id).as_str(),
+                        archetype, 30.0, 10);
+                    self.minion_id += 1;
+
+                    let angle = index as f64 * 360.0 / BOSS_SUMMON_COUNT as f64;
+                    minion.enemy_entity.set_sprite(51, 43);
+                    // vicino a un muro esterno i minion verrebbero spawnati fuori dal livello
+                    minion.enemy_entity.set_position(game_utils.clamp_to_level(self.boss_entity.get_position()
+                        + Utils::rotate_point(FPoint::new(BOSS_COLLISION_RADIUS * 2.0, 0.0), angle), LEVEL_SPAWN_MARGIN));
+                    self.pending_spawns.push(Box::new(minion));
+                }
+            },
+        }
+    }
+}
+
+impl GameObject for Boss{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn draw(&mut self, canvas:&mut WindowCanvas, texture:&Texture, animation_frame:u32, game_utils:&Utils, scale_factor:f32) -> Result<(), String> {
+        // durante la preparazione della carica lo sprite pulsa
+        let pulse = match self.state{
+            BossState::ChargeWindup(time_left, _) => 1.0 + 0.15 * (time_left * 30.0).sin().abs(),
+            _ => 1.0,
+        };
+        self.boss_entity.draw(canvas, texture, animation_frame, game_utils, scale_factor * BOSS_SCALE * pulse)
+    }
+
+    fn get_name(&self) -> &str {
+        self.boss_entity.get_name()
+    }
+
+    fn update(&mut self, deltatime:f32, game_utils:&Utils) {
+        match self.state{
+            BossState::Chasing => {
+                let target_direction = Utils::point_normalized(game_utils.get_player_position() - self.boss_entity.get_position());
+                self.boss_entity.change_direction(target_direction);
+                if Utils::calculate_point_distance(self.boss_entity.position, game_utils.get_player_position()) > BOSS_COLLISION_RADIUS{
+                    self.boss_entity.update_with_walls(deltatime, game_utils, BOSS_COLLISION_RADIUS);
+                }
+
+                self.attack_cooldown -= deltatime;
+                if self.attack_cooldown <= 0.0{
+                    let attack = BossAttack::ALL[self.next_attack];
+                    self.next_attack = (self.next_attack + 1) % self.phase as usize; // solo gli attacchi sbloccati
+                    self.attack_cooldown = BOSS_ATTACK_COOLDOWN * (1.0 - 0.2 * (self.phase - 1) as f32);
+                    self.start_attack(attack, game_utils);
+                }
+            },
+            BossState::ChargeWindup(time_left, direction) => {
+                self.boss_entity.set_rotation(direction.y.atan2(direction.x).to_degrees() as f64);
+                self.state = if time_left - deltatime <= 0.0{
+                    BossState::Charging(BOSS_CHARGE_TIME, direction)
+                }else{
+                    BossState::ChargeWindup(time_left - deltatime, direction)
+                };
+            },
+            BossState::Charging(time_left, direction) => {
+                let from = self.boss_entity.get_position();
+                let target = from + direction * BOSS_CHARGE_SPEED * deltatime;
+                let position = game_utils.move_with_walls(from, target, BOSS_COLLISION_RADIUS);
+                self.boss_entity.set_position(position);
+
+                // la carica finisce prima se si sbatte contro un muro
+                let blocked = Utils::calculate_point_distance(position, target) > 1.0;
+                self.state = if blocked || time_left - deltatime <= 0.0{
+                    BossState::Chasing
+                }else{
+                    BossState::Charging(time_left - deltatime, direction)
+                };
+            },
+        }
+    }
+
+    fn is_destroyed(&self) -> bool {
+        self.current_health <= 0
+    }
+}
+
+impl Damageable for Boss{
+    fn take_damage(&mut self, damage:&DamageInfo) -> DamageInfo {
+        let resistances = self.get_resistances();
+        let resolved = self.damage_pipeline.resolve(damage, &resistances);
+        self.current_health -= resolved.amount;
+        self.last_hit_direction = resolved.direction;
+        if self.current_health <= 0 && self.killed_by.is_none(){
+            self.killed_by = Some(resolved.source);
+        }
+        self.update_phase();
+        resolved
+    }
+
+    fn get_current_health(&self) -> i32 {
+        self.current_health
+    }
+
+    fn get_max_health(&self) -> i32 {
+        self.health
+    }
+
+    fn get_entity(&self) -> &Entity {
+        &self.boss_entity
+    }
+
+    fn get_resistances(&self) -> Resistances {
+        self.resistances
+    }
+}
+
+// ------------- SCORE -------------
+
+const COMBO_WINDOW:f32 = 2.5; // secondi entro cui la prossima uccisione continua la combo
+const COMBO_KILLS_PER_STEP:u32 = 5; // uccisioni consecutive per aumentare il moltiplicatore di 0.5
+const COMBO_MAX_MULTIPLIER:f32 = 4.0;
+
+// combo di uccisioni: ogni uccisione entro COMBO_WINDOW secondi dalla precedente la allunga e ne fa salire il
+// moltiplicatore, se il tempo scade la combo riparte da zero
+pub struct ComboCounter{
+    combo:u32,
+    time_left:f32,
+    pub best_combo:u32,
+}
+
+impl ComboCounter{
+    pub fn new() -> Self{
+        ComboCounter{
+            combo:0,
+            time_left:0.0,
+            best_combo:0,
+        }
+    }
+
+    pub fn update(&mut self, deltatime:f32){
+        if self.combo > 0{
+            self.time_left -= deltatime;
+            if self.time_left <= 0.0{
+                self.reset();
+            }
+        }
+    }
+
+    // registra un'uccisione e restituisce i punti da aggiungere allo score
+    pub fn register_kill(&mut self, base_score:i32) -> i32{
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+        self.time_left = COMBO_WINDOW;
+        (base_score as f32 * self.get_multiplier()).round() as i32
+    }
+
+    pub fn reset(&mut self){
+        self.combo = 0;
+        self.time_left = 0.0;
+    }
+
+    pub fn get_combo(&self) -> u32{
+        self.combo
+    }
+
+    pub fn get_multiplier(&self) -> f32{
+        let steps = self.combo.saturating_sub(1) / COMBO_KILLS_PER_STEP;
+        (1.0 + 0.5 * steps as f32).min(COMBO_MAX_MULTIPLIER)
+    }
+
+    // tempo rimasto per continuare la combo tra 0.0 e 1.0 (per la barra dell'HUD)
+    pub fn get_time_fraction(&self) -> f32{
+        (self.time_left / COMBO_WINDOW).clamp(0.0, 1.0)
+    }
+}
+
+// record salvati su file: score migliore in modalita' infinita e in modalita' ondate (con l'ondata raggiunta)
+pub struct HighScore{
+    pub best_score:i32,
+    pub best_wave_score:i32,
+    pub best_wave:u32,
+}
+
+impl HighScore{
+    pub fn new() -> Self{
+        HighScore{
+            best_score:0,
+            best_wave_score:0,
+            best_wave:0,
+        }
+    }
+
+    pub fn load(path:&str) -> Result<Self, String>{
+        let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut high_score = HighScore::new();
+
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')){
+            let parsed = line.split_once('=').map(|(key, value)| (key.trim(), value.trim()));
+            match parsed{
+                Some(("best_score", value)) => high_score.best_score = value.parse().unwrap_or(0),
+                Some(("best_wave_score", value)) => high_score.best_wave_score = value.parse().unwrap_or(0),
+                Some(("best_wave", value)) => high_score.best_wave = value.parse().unwrap_or(0),
+                _ => println!("{}: riga non valida: {}", path, line),
+            }
+        }
+
+        Ok(high_score)
+    }
+
+    pub fn save(&self, path:&str) -> Result<(), String>{
+        let content = format!("best_score = {}\nbest_wave_score = {}\nbest_wave = {}\n", self.best_score, self.best_wave_score, self.best_wave);
+        std::fs::write(path, content).map_err(|error| error.to_string())
+    }
+
+    // aggiorna il record della partita appena finita (wave = None in modalita' infinita), true se e' un nuovo record
+    pub fn record(&mut self, score:i32, wave:Option<u32>) -> bool{
+        match wave{
+            Some(wave) => {
+                // ondata e score sono un unico record della stessa partita: conta prima l'ondata raggiunta, poi lo score
+                let new_record = (wave, score) > (self.best_wave, self.best_wave_score);
+                if new_record{
+                    self.best_wave = wave;
+                    self.best_wave_score = score;
+                }
+                new_record
+            },
+            None => {
+                let new_record = score > self.best_score;
+                self.best_score = self.best_score.max(score);
+                new_record
+            },
+        }
+    }
+}
+
+// ------------- ONDATE -------------
+
+const WAVE_FIRST_INTERMISSION:f32 = 3.0; // secondi prima della prima ondata
+const WAVE_INTERMISSION:f32 = 6.0; // secondi di pausa tra un'ondata e l'altra
+const WAVE_SPAWN_INTERVAL:f32 = 0.5; // secondi tra uno spawn e l'altro durante l'ondata
+const WAVE_BANNER_TIME:f32 = 2.5;
+
+// composizione di un'ondata: quanti nemici per archetipo e quanto sono piu' resistenti
+pub struct WaveDefinition{
+    pub composition:Vec<(EnemyArchetype, u32)>,
+    pub health_multiplier:f32,
+    pub clear_bonus:i32, // punti dati quando l'ondata viene completata
+}
+
+impl WaveDefinition{
+    // dalla seconda ondata arrivano i runner, dalla terza i brute
+    pub fn for_wave(wave:u32) -> Self{
+        WaveDefinition{
+            composition:vec![
+                (EnemyArchetype::Grunt, 3 + wave * 2),
+                (EnemyArchetype::Runner, wave.saturating_sub(1) * 2),
+                (EnemyArchetype::Brute, wave.saturating_sub(2)),
+            ],
+            health_multiplier:1.0 + 0.15 * wave.saturating_sub(1) as f32,
+            clear_bonus:100 * wave as i32,
+        }
+    }
+
+    pub fn get_enemy_count(&self) -> u32{
+        self.composition.iter().map(|(_, count)| count).sum()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum WaveState{
+    Intermission(f32), // secondi rimasti prima della prossima ondata
+    InProgress,
+}
+
+pub struct WaveManager{
+    wave:u32, // ondata attuale (0 prima della prima)
+    state:WaveState,
+    pending:Vec<EnemyArchetype>, // nemici dell'ondata ancora da spawnare
+    spawn_timer:f32,
+    health_multiplier:f32,
+    clear_bonus:i32,
+    enemy_count:u32, // nemici totali dell'ondata attuale
+    enemies_alive:usize,
+    banner_time_left:f32,
+    cleared_wave:Option<(u32, i32)>, // (ondata, bonus) completata dall'ultimo take_cleared_wave()
+    last_clear:Option<(u32, i32)>, // ultima ondata completata, mostrata durante l'intervallo
+}
+
+impl WaveManager{
+    pub fn new() -> Self{
+        WaveManager{
+            wave:0,
+            state:WaveState::Intermission(WAVE_FIRST_INTERMISSION),
+            pending:Vec::new(),
+            spawn_timer:0.0,
+            health_multiplier:1.0,
+            clear_bonus:0,
+            enemy_count:0,
+            enemies_alive:0,
+            banner_time_left:0.0,
+            cleared_wave:None,
+            last_clear:None,
+        }
+    }
+
+    fn start_next_wave(&mut self){
+        self.wave += 1;
+        let definition = WaveDefinition::for_wave(self.wave);
+
+        self.pending.clear();
+        for (archetype, count) in definition.composition.iter(){
+            for _ in 0..*count{
+                self.pending.push(*archetype);
+            }
+        }
+        // ordine casuale, cosi' gli archetipi arrivano mescolati
+        for index in (1..self.pending.len()).rev(){
+            self.pending.swap(index, rand::thread_rng().gen_range(0..=index));
+        }
+
+        self.health_multiplier = definition.health_multiplier;
+        self.clear_bonus = definition.clear_bonus;
+        self.enemy_count = definition.get_enemy_count();
+        self.spawn_timer = 0.0;
+        self.banner_time_left = WAVE_BANNER_TIME;
+        self.state = WaveState::InProgress;
+    }
+
+    // enemies_alive = nemici ancora nella lista dei gameobjects. Restituisce i nemici da aggiungere in questo frame
+    pub fn update(&mut self, deltatime:f32, enemies_alive:usize, spawner:&mut EnemySpawner, game_utils:&Utils) -> Option<Vec<Enemy>>{
+        self.enemies_alive = enemies_alive;
+        self.banner_time_left = (self.banner_time_left - deltatime).max(0.0);
+
+        match self.state{
+            WaveState::Intermission(time_left) => {
+                if time_left - deltatime <= 0.0{
+                    self.start_next_wave();
+                }else{
+                    self.state = WaveState::Intermission(time_left - deltatime);
+                }
+                None
+            },
+            WaveState::InProgress => {
+                if self.pending.is_empty(){
+                    if enemies_alive == 0{
+                        self.cleared_wave = Some((self.wave, self.clear_bonus));
+                        self.last_clear = Some((self.wave, self.clear_bonus));
+                        self.state = WaveState::Intermission(WAVE_INTERMISSION);
+                    }
+                    return None;
+                }
+
+                self.spawn_timer -= deltatime;
+                if self.spawn_timer > 0.0{
+                    return None;
+                }
+                self.spawn_timer = WAVE_SPAWN_INTERVAL;
+
+                let archetype = self.pending.pop()?;
+                Some(vec![spawner.spawn_archetype(archetype, self.health_multiplier, game_utils)])
+            },
+        }
+    }
+
+    // ondata completata dall'ultima chiamata (ondata, bonus), come Player::take_recent_damage
+    pub fn take_cleared_wave(&mut self) -> Option<(u32, i32)>{
+        self.cleared_wave.take()
+    }
+
+    pub fn get_wave(&self) -> u32{
+        self.wave
+    }
+
+    pub fn get_state(&self) -> WaveState{
+        self.state
+    }
+
+    // nemici ancora da spawnare + quelli vivi
+    pub fn get_remaining_enemies(&self) -> usize{
+        self.pending.len() + self.enemies_alive
+    }
+
+    pub fn get_enemy_count(&self) -> u32{
+        self.enemy_count
+    }
+
+    pub fn get_last_clear(&self) -> Option<(u32, i32)>{
+        self.last_clear
+    }
+
+    // opacita' del banner "Wave N" all'inizio dell'ondata (0 = nascosto), sparisce nell'ultimo secondo
+    pub fn get_banner_alpha(&self) -> u8{
+        (self.banner_time_left.min(1.0) * 255.0) as u8
+    }
+}
+
+// ------------- DIRECTOR -------------
+
+// curva da intensita' (0.0 - 1.0) a valore: min a intensita' 0, max a intensita' 1, exponent cambia la forma
+// (1.0 lineare, > 1.0 cresce piu' tardi, < 1.0 cresce subito)
+#[derive(Clone, Copy, Debug)]
+pub struct DifficultyCurve{
+    pub min:f32,
+    pub max:f32,
+    pub exponent:f32,
+}
+
+impl DifficultyCurve{
+    pub fn new(min:f32, max:f32, exponent:f32) -> Self{
+        DifficultyCurve{ min, max, exponent }
+    }
+
+    pub fn evaluate(&self, intensity:f32) -> f32{
+        self.min + (self.max - self.min) * intensity.clamp(0.0, 1.0).powf(self.exponent)
+    }
+}
+
+pub struct DirectorConfig{
+    pub time_to_max:f32, // secondi di partita per arrivare al massimo della progressione
+    pub score_to_max:f32, // score con cui si arriva al massimo della progressione
+    pub time_weight:f32, // peso del tempo nella progressione (il resto e' dato dallo score)
+
+    pub spawn_interval:DifficultyCurve, // secondi tra uno spawn e l'altro
+    pub enemies_per_spawn:DifficultyCurve,
+    pub enemy_health:DifficultyCurve,
+    pub enemy_speed:DifficultyCurve,
+    pub archetype_weights:[DifficultyCurve; 3], // stesso ordine di EnemyArchetype::ALL
+
+    pub stress_per_damage:f32, // stress aggiunto per ogni punto di danno preso
+    pub stress_decay:f32, // stress tolto al secondo
+    pub peak_stress:f32, // stress oltre il quale finisce il build-up
+    pub max_build_up_time:f32, // dopo questo tempo si passa comunque al picco
+    pub peak_time:f32,
+    pub relax_time:f32,
+    pub relax_intensity:f32, // frazione della progressione usata durante il relax
+    pub smoothing:f32, // velocita' con cui l'intensita' segue il valore richiesto
+}
+
+impl DirectorConfig{
+    pub fn new() -> Self{
+        DirectorConfig{
+            time_to_max:600.0,
+            score_to_max:3000.0,
+            time_weight:0.5,
+            spawn_interval:DifficultyCurve::new(5.5, 1.2, 0.8),
+            enemies_per_spawn:DifficultyCurve::new(1.5, 6.0, 1.2),
+            enemy_health:DifficultyCurve::new(7.0, 40.0, 1.0),
+            enemy_speed:DifficultyCurve::new(22.0, 70.0, 1.0),
+            archetype_weights:[
+                DifficultyCurve::new(1.0, 0.4, 1.0), // grunt
+                DifficultyCurve::new(0.1, 0.35, 1.0), // runner
+                DifficultyCurve::new(0.0, 0.25, 1.5), // brute
+            ],
+            stress_per_damage:0.02,
+            stress_decay:0.05,
+            peak_stress:0.6,
+            max_build_up_time:45.0,
+            peak_time:8.0,
+            relax_time:12.0,
+            relax_intensity:0.4,
+            smoothing:0.5,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DirectorPhase{
+    BuildUp, // l'intensita' sale seguendo la progressione
+    Peak, // breve picco sopra la progressione
+    Relax, // pausa dopo il picco (o se il player e' sotto pressione), pochi nemici
+}
+
+// regola lo spawner in base a quanto e' andata avanti la partita (tempo, score) e a quanto il player e' in
+// difficolta' (vita, danni presi di recente), alternando fasi di build-up, picco e relax
+pub struct DifficultyDirector{
+    pub config:DirectorConfig,
+    elapsed:f32,
+    stress:f32, // 0.0 - 1.0, sale con i danni presi e con la vita bassa
+    intensity:f32, // 0.0 - 1.0, valore usato dalle curve
+    phase:DirectorPhase,
+    phase_time:f32,
+}
+
+impl DifficultyDirector{
+    pub fn new(config:DirectorConfig) -> Self{
+        DifficultyDirector{
+            config,
+            elapsed:0.0,
+            stress:0.0,
+            intensity:0.0,
+            phase:DirectorPhase::BuildUp,
+            phase_time:0.0,
+        }
+    }
+
+    fn set_phase(&mut self, phase:DirectorPhase){
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+
+    // recent_damage = danno preso dal player in questo frame
+    pub fn update(&mut self, deltatime:f32, score:i32, player_health_fraction:f32, recent_damage:i32){
+        self.elapsed += deltatime;
+        self.phase_time += deltatime;
+
+        // progressione della partita tra 0 e 1
+        let time_progress = (self.elapsed / self.config.time_to_max).min(1.0);
+        let score_progress = (score as f32 / self.config.score_to_max).clamp(0.0, 1.0);
+        let progress = time_progress * self.config.time_weight + score_progress * (1.0 - self.config.time_weight);
+
+        // stress: i danni lo alzano subito, poi scende lentamente ma mai sotto quello dato dalla vita mancante
+        let health_stress = (1.0 - player_health_fraction.clamp(0.0, 1.0)) * 0.5;
+        self.stress = (self.stress + recent_damage as f32 * self.config.stress_per_damage - self.config.stress_decay * deltatime)
+            .max(health_stress).clamp(0.0, 1.0);
+
+        match self.phase{
+            DirectorPhase::BuildUp => {
+                if self.stress >= self.config.peak_stress || self.phase_time >= self.config.max_build_up_time{
+                    self.set_phase(DirectorPhase::Peak);
+                }
+            },
+            DirectorPhase::Peak => {
+                if self.phase_time >= self.config.peak_time{
+                    self.set_phase(DirectorPhase::Relax);
+                }
+            },
+            DirectorPhase::Relax => {
+                // si riparte solo quando il player si e' ripreso
+                if self.phase_time >= self.config.relax_time && self.stress < self.config.peak_stress * 0.5{
+                    self.set_phase(DirectorPhase::BuildUp);
+                }
+            },
+        }
+
+        let target = match self.phase{
+            DirectorPhase::BuildUp => progress * (1.0 - 0.5 * self.stress),
+            DirectorPhase::Peak => (progress + 0.15).min(1.0),
+            DirectorPhase::Relax => progress * self.config.relax_intensity,
+        };
+        self.intensity += (target - self.intensity) * (1.0 - (-self.config.smoothing * deltatime).exp());
+    }
+
+    // imposta i parametri dello spawner in base all'intensita' attuale
+    pub fn apply(&self, spawner:&mut EnemySpawner){
+        let spawn_interval = self.config.spawn_interval.evaluate(self.intensity).max(0.1);
+        spawner.spawn_rate = (spawn_interval * 0.8, spawn_interval * 1.2);
+
+        let enemies_per_spawn = self.config.enemies_per_spawn.evaluate(self.intensity).max(1.0);
+        spawner.enemies_per_spawn = (enemies_per_spawn.floor() as u32, enemies_per_spawn.ceil() as u32);
+
+        let health = self.config.enemy_health.evaluate(self.intensity).max(1.0) as i32;
+        spawner.health_enemies = (health, health + 5);
+
+        let speed = self.config.enemy_speed.evaluate(self.intensity);
+        spawner.speed_enemies = (speed, speed + 5.0);
+
+        for (weight, curve) in spawner.archetype_weights.iter_mut().zip(self.config.archetype_weights.iter()){
+            *weight = curve.evaluate(self.intensity);
+        }
+    }
+
+    pub fn get_intensity(&self) -> f32{
+        self.intensity
+    }
+
+    pub fn get_phase(&self) -> DirectorPhase{
+        self.phase
+    }
+}
+
+// ------------- PICKUP -------------
+
+const PICKUP_LIFETIME:f32 = 10.0; // secondi prima che il pickup sparisca
+const PICKUP_BLINK_TIME:f32 = 3.0; // negli ultimi secondi lampeggia
+const PICKUP_SIZE:f32 = 12.0;
+const PICKUP_COLLECT_RADIUS:f32 = 18.0;
+const PICKUP_MAGNET_RADIUS:f32 = 90.0; // sotto questa distanza il pickup viene attirato dal player
+const PICKUP_MAGNET_SPEED:f32 = 260.0; // velocita' massima (a distanza 0)
+
+// effetto del pickup con la sua intensita' (vita curata, colpi, durata in secondi, valuta)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PickupKind{
+    Health(i32),
+    Ammo(i32), // colpi esplosivi
+    RapidFire(f32),
+    SpeedBoost(f32),
+    Currency(i32),
+    BulletPower(BulletPower, f32),
+}
+
+impl PickupKind{
+    pub fn get_color(&self) -> Color{
+        match self{
+            PickupKind::Health(_) => Color::RGB(60, 220, 90),
+            PickupKind::Ammo(_) => Color::RGB(255, 150, 40),
+            PickupKind::RapidFire(_) => Color::RGB(230, 60, 230),
+            PickupKind::SpeedBoost(_) => Color::RGB(80, 180, 255),
+            PickupKind::Currency(_) => Color::RGB(255, 215, 0),
+            PickupKind::BulletPower(_, _) => Color::RGB(240, 240, 240),
+        }
+    }
+}
+
+// tabella dei drop: (pickup, probabilita' tra 0.0 e 1.0)
+pub struct DropTable{
+    pub entries:Vec<(PickupKind, f32)>,
+}
+
+impl DropTable{
+    pub fn new(entries:Vec<(PickupKind, f32)>) -> Self{
+        DropTable{ entries }
+    }
+
+    pub fn roll<R:Rng>(&self, rng:&mut R) -> Vec<PickupKind>{
+        self.entries.iter()
+            .filter(|(_, chance)| rng.gen_bool(chance.clamp(0.0, 1.0) as f64))
+            .map(|(kind, _)| *kind)
+            .collect()
+    }
+}
+
+pub struct Pickup{
+    pickup_entity:Entity,
+    pub kind:PickupKind,
+    age:f32,
+    collected:bool,
+}
+
+impl Pickup{
+    pub fn new(kind:PickupKind, position:FPoint) -> Self{
+        let mut pickup_entity = Entity::new("pickup", EntityType::Item);
+        pickup_entity.set_position(position);
+        Pickup{
+            pickup_entity,
+            kind,
+            age:0.0,
+            collected:false,
+        }
+    }
+
+    pub fn is_collected(&self) -> bool{
+        self.collected
+    }
+
+    pub fn get_position(&self) -> FPoint{
+        self.pickup_entity.get_position()
+    }
+}
+
+impl GameObject for Pickup{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    // quadrato colorato in base al tipo (non serve una texture)
+    fn draw(&mut self, canvas:&mut WindowCanvas, _texture:&Texture, _animation_frame:u32, game_utils:&Utils, _scale_factor:f32) -> Result<(), String> {
+        let time_left = PICKUP_LIFETIME - self.age;
+        if time_left < PICKUP_BLINK_TIME && (time_left * 8.0) as i32 % 2 == 1{
+            return Ok(());
+        }
+
+        // leggero movimento su e giu'
+        let bob = (self.age * 4.0).sin() * 2.0;
+        let screen_position = game_utils.world_to_screen(self.pickup_entity.get_position() + FPoint::new(0.0, bob));
+        let size = PICKUP_SIZE * game_utils.main_camera_zoom;
+        let rect = Rect::new((screen_position.x - size / 2.0).round() as i32, (screen_position.y - size / 2.0).round() as i32,
+            size.round() as u32, size.round() as u32);
+
+        canvas.set_draw_color(self.kind.get_color());
+        canvas.fill_rect(rect)?;
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        canvas.draw_rect(rect)?;
+
+        Ok(())
+    }
+
+    fn get_name(&self) -> &str {
+        self.pickup_entity.get_name()
+    }
+
+    fn update(&mut self, deltatime:f32, game_utils:&Utils) {
+        self.age += deltatime;
+
+        let to_player = game_utils.get_player_position() - self.pickup_entity.get_position();
+        let distance = Utils::point_magnitude(to_player);
+
+        if distance < PICKUP_COLLECT_RADIUS{
+            self.collected = true;
+        }else if distance < PICKUP_MAGNET_RADIUS{
+            // piu' si e' vicini piu' il pickup accelera verso il player
+            let pull = 1.0 - distance / PICKUP_MAGNET_RADIUS;
+            let step = (PICKUP_MAGNET_SPEED * pull * deltatime).min(distance);
+            self.pickup_entity.set_position(self.pickup_entity.get_position() + Utils::point_normalized(to_player) * step);
+        }
+    }
+
+    fn is_destroyed(&self) -> bool {
+        self.collected || self.age >= PICKUP_LIFETIME
+    }
+}
+
+// ------------- DECALS -------------
+
+const CORPSE_DEATH_ANIMATION_TIME:f32 = 0.3; // secondi in cui il corpo "cade" prima di restare a terra
+const CORPSE_LIFETIME:f32 = 8.0;
+const BLOOD_DECAL_LIFETIME:f32 = 12.0;
+const DECAL_FADE_TIME:f32 = 2.0; // secondi finali in cui il decal sparisce gradualmente
+const BLOOD_DECAL_SIZE:(f32, f32) = (26.0, 40.0); // min e max
+
+enum DecalKind{
+    Corpse{ sprite:Rect }, // frame dello spritesheet "default"
+    Blood, // texture "particle" colorata e schiacciata
+}
+
+struct Decal{
+    kind:DecalKind,
+    position:FPoint,
+    rotation:f64,
+    size:(f32, f32),
+    age:f32,
+    lifetime:f32,
+}
+
+// livello a terra (disegnato sotto a player e nemici) con i corpi e le macchie di sangue lasciate dai nemici.
+// Il numero di decal e' limitato, quando si supera il limite si ricicla il piu' vecchio
+pub struct DecalLayer{
+    decals:VecDeque<Decal>,
+    capacity:usize,
+}
+
+impl DecalLayer{
+    pub fn new(capacity:usize) -> Self{
+        DecalLayer{
+            decals:VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, decal:Decal){
+        if self.capacity == 0{
+            return;
+        }
+        if self.decals.len() >= self.capacity{
+            self.decals.pop_front();
+        }
+        self.decals.push_back(decal);
+    }
+
+    // corpo del nemico con animazione di morte e macchia di sangue spostata nella direzione del colpo
+    pub fn add_enemy_death(&mut self, death:&EnemyDeath){
+        let blood_size = rand::thread_rng().gen_range(BLOOD_DECAL_SIZE.0..BLOOD_DECAL_SIZE.1);
+        self.push(Decal{
+            kind:DecalKind::Blood,
+            position:death.position + death.hit_direction * (blood_size * 0.4),
+            rotation:rand::thread_rng().gen_range(0.0..360.0),
+            size:(blood_size, blood_size * 0.7),
+            age:0.0,
+            lifetime:BLOOD_DECAL_LIFETIME,
+        });
+
+        // il corpo cade nella direzione del colpo, se non c'e' (es. morto per contatto) si tiene la rotazione attuale
+        let rotation = if death.hit_direction != FPoint::new(0.0, 0.0){
+            death.hit_direction.y.atan2(death.hit_direction.x).to_degrees() as f64
+        }else{
+            death.rotation
+        };
+        self.push(Decal{
+            kind:DecalKind::Corpse{ sprite:death.sprite },
+            position:death.position,
+            rotation,
+            size:(death.sprite.width() as f32, death.sprite.height() as f32),
+            age:0.0,
+            lifetime:CORPSE_LIFETIME,
+        });
+    }
+
+    pub fn update(&mut self, deltatime:f32){
+        for decal in self.decals.iter_mut(){
+            decal.age += deltatime;
+        }
+        self.decals.retain(|decal| decal.age < decal.lifetime);
+    }
+
+    pub fn draw(&self, canvas:&mut WindowCanvas, resource_manager:&mut ResourceManager, game_utils:&Utils) -> Result<(), String>{
+        for decal in self.decals.iter(){
+            let fade = ((decal.lifetime - decal.age) / DECAL_FADE_TIME).clamp(0.0, 1.0);
+
+            // animazione di morte: flash bianco che diventa scuro, il corpo si allarga e si schiaccia a terra
+            let (texture_name, source, color, scale, extra_rotation) = match &decal.kind{
+                DecalKind::Corpse { sprite } => {
+                    let progress = (decal.age / CORPSE_DEATH_ANIMATION_TIME).clamp(0.0, 1.0);
+                    let color = Utils::lerp_color(Color::RGBA(255, 255, 255, 255), Color::RGBA(110, 60, 60, 255), progress);
+                    let scale = (1.0 + 0.25 * (progress * std::f32::consts::PI).sin(), 1.0 - 0.2 * progress);
+                    ("default", Some(*sprite), color, scale, 90.0 * progress as f64)
+                },
+                DecalKind::Blood => {
+                    ("particle", Some(Rect::new(0, 0, PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE)), Color::RGBA(110, 0, 0, 220), (1.0, 1.0), 0.0)
+                },
+            };
+
+            let width = decal.size.0 * scale.0 * game_utils.main_camera_zoom;
+            let height = decal.size.1 * scale.1 * game_utils.main_camera_zoom;
+            let screen_position = game_utils.world_to_screen(decal.position);
+            // culling come per le entity
+            if screen_position.x + width < 0.0 || screen_position.y + width < 0.0
+                || screen_position.x - width > game_utils.screen_size.0 as f32 || screen_position.y - width > game_utils.screen_size.1 as f32{
+                continue;
+            }
+
+            let texture = resource_manager.get_texture_mut(texture_name)
+                .ok_or(format!("Texture {} non caricata", texture_name))?;
+            texture.set_blend_mode(BlendMode::Blend); // la texture delle particelle puo' essere rimasta in additivo
+            texture.set_color_mod(color.r, color.g, color.b);
+            texture.set_alpha_mod((color.a as f32 * fade) as u8);
+            let target = Rect::new((screen_position.x - width / 2.0).round() as i32, (screen_position.y - height / 2.0).round() as i32,
+                width.round().max(1.0) as u32, height.round().max(1.0) as u32);
+            let result = canvas.copy_ex(&*texture, source, target, decal.rotation + extra_rotation + game_utils.main_camera_rotation,
+                Point::new(target.width() as i32 / 2, target.height() as i32 / 2), false, false);
+            // le texture sono condivise con nemici e particelle, si ripristinano
+            texture.set_color_mod(255, 255, 255);
+            texture.set_alpha_mod(255);
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+// ------------- NUMERI DEL DANNO -------------
+
+const DAMAGE_NUMBER_LIFETIME:f32 = 0.8; // secondi
+const DAMAGE_NUMBER_RISE_SPEED:f32 = 60.0; // pixel al secondo verso l'alto all'inizio
+const DAMAGE_NUMBER_CRIT_SCALE:f32 = 1.6;
+
+struct DamageNumber{
+    text:String, // formattato una sola volta alla creazione, il buffer viene riutilizzato dal pool
+    position:FPoint, // world space
+    velocity:FPoint,
+    color:Color,
+    scale:f32,
+    age:f32,
+}
+
+impl DamageNumber{
+    fn empty() -> Self{
+        DamageNumber{
+            text:String::with_capacity(8), // abbastanza per qualsiasi danno, cosi' il testo non rialloca
+            position:FPoint::new(0.0, 0.0),
+            velocity:FPoint::new(0.0, 0.0),
+            color:Color::RGB(255, 255, 255),
+            scale:1.0,
+            age:0.0,
+        }
+    }
+}
+
+// testo fluttuante che sale e sparisce nel punto colpito. Il testo viene renderizzato in bianco tramite la cache
+// dei testi (i valori del danno sono pochi, quindi le texture vengono riutilizzate) e colorato con color/alpha mod
+pub struct DamageNumbers{
+    numbers:Pool<DamageNumber>, // quando e' pieno si ricicla il numero piu' vecchio
+    font:(&'static str, u16),
+}
+
+impl DamageNumbers{
+    pub fn new(capacity:usize, font:(&'static str, u16)) -> Self{
+        DamageNumbers{
+            numbers:Pool::new(capacity, false, DamageNumber::empty),
+            font,
+        }
+    }
+
+    pub fn get_pool_stats(&self) -> PoolStats{
+        self.numbers.get_stats()
+    }
+
+    pub fn spawn(&mut self, damage:&DamageInfo, world_position:FPoint){
+        if damage.amount <= 0{
+            return;
+        }
+
+        if self.numbers.is_full(){
+            let oldest = self.numbers.iter_slots()
+                .max_by(|(_, a), (_, b)| a.age.total_cmp(&b.age))
+                .map(|(slot, _)| slot);
+            if let Some(oldest) = oldest{
+                self.numbers.release(oldest);
+            }
+        }
+
+        let number = match self.numbers.acquire(){
+            Some(number) => number,
+            None => return, // capacita' 0
+        };
+
+        // piccola deviazione orizzontale casuale in modo che colpi ravvicinati non si sovrappongano
+        let horizontal_speed = rand::thread_rng().gen_range(-25.0..25.0);
+        number.text.clear();
+        let _ = if damage.critical { write!(number.text, "{}!", damage.amount) } else { write!(number.text, "{}", damage.amount) };
+        number.position = world_position;
+        number.velocity = FPoint::new(horizontal_speed, -DAMAGE_NUMBER_RISE_SPEED);
+        number.color = damage.damage_type.get_color();
+        number.scale = if damage.critical { DAMAGE_NUMBER_CRIT_SCALE } else { 1.0 };
+        number.age = 0.0;
+    }
+
+    pub fn update(&mut self, deltatime:f32){
+        self.numbers.retain(|number| {
+            number.age += deltatime;
+            number.position += number.velocity * deltatime;
+            number.velocity *= (1.0 - 2.0 * deltatime).max(0.0); // rallenta salendo
+            number.age < DAMAGE_NUMBER_LIFETIME
+        });
+    }
+
+    pub fn draw(&self, canvas:&mut WindowCanvas, resource_manager:&mut ResourceManager, game_utils:&Utils) -> Result<(), String>{
+        for number in self.numbers.iter(){
+            let progress = (number.age / DAMAGE_NUMBER_LIFETIME).clamp(0.0, 1.0);
+            let alpha = ((1.0 - progress * progress) * 255.0) as u8; // resta visibile e sparisce verso la fine
+            let pop = 1.0 + 0.4 * (1.0 - progress * 8.0).max(0.0); // ingrandimento appena creato
+
+            let texture = resource_manager.get_text_texture_mut(self.font.0, self.font.1, Color::RGB(255, 255, 255), &number.text)?;
+            let query = texture.query();
+            let width = query.width as f32 * number.scale * pop;
+            let height = query.height as f32 * number.scale * pop;
+            let screen_position = game_utils.world_to_screen(number.position);
+
+            texture.set_color_mod(number.color.r, number.color.g, number.color.b);
+            texture.set_alpha_mod(alpha);
+            let result = canvas.copy(texture, None, Some(Rect::new((screen_position.x - width / 2.0).round() as i32,
+                (screen_position.y - height / 2.0).round() as i32, width.round() as u32, height.round() as u32)));
+            // la texture e' condivisa con gli altri testi bianchi (es. HUD), si ripristina prima di propagare errori
+            texture.set_color_mod(255, 255, 255);
+            texture.set_alpha_mod(255);
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+// ------------- AUDIO -------------
+
+// gestione di effetti sonori e musica tramite SDL2_mixer. Se il driver di default non parte o non apre un
+// dispositivo (es. macchina senza scheda audio) si riprova con il driver "dummy"; se fallisce anche quello
+// l'audio viene disattivato e tutte le chiamate diventano no-op, senza bloccare il gioco
+pub struct AudioManager{
+    _audio_subsystem:Option<AudioSubsystem>, // devono vivere quanto l'AudioManager
+    _mixer_context:Option<Sdl2MixerContext>,
+    enabled:bool,
+    master_volume:f32, // volumi tra 0.0 e 1.0
+    sfx_volume:f32,
+    music_volume:f32,
+    pub max_distance:f32, // oltre questa distanza dal centro della camera un suono non si sente
+    pending_sounds:Vec<(String, FPoint)>, // suoni richiesti durante il frame, riprodotti in flush()
+}
+
+impl AudioManager{
+    pub fn new(sdl_context:&Sdl) -> Self{
+        let mut audio_manager = AudioManager{
+            _audio_subsystem:None,
+            _mixer_context:None,
+            enabled:false,
+            master_volume:1.0,
+            sfx_volume:0.8,
+            music_volume:0.5,
+            max_distance:700.0,
+            pending_sounds:Vec::new(),
+        };
+
+        // se il driver di default non funziona si riprova con il driver dummy (nessun output ma API funzionante)
+        let audio_subsystem = sdl_context.audio().or_else(|error| {
+            println!("Audio non disponibile ({}), uso il driver dummy", error);
+            sdl2::hint::set("SDL_AUDIODRIVER", "dummy");
+            sdl_context.audio()
+        });
+
+        let mut audio_subsystem = match audio_subsystem{
+            Ok(audio_subsystem) => audio_subsystem,
+            Err(error) => {
+                println!("Audio disattivato: {}", error);
+                return audio_manager;
+            }
+        };
+
+        // il driver puo' partire ma senza dispositivo di output: in quel caso si reinizializza il sottosistema
+        // con il driver dummy (il driver viene scelto all'inizializzazione) e si riapre il mixer
+        if let Err(error) = AudioManager::open_mixer(){
+            println!("Dispositivo audio non disponibile ({}), uso il driver dummy", error);
+            drop(audio_subsystem);
+            sdl2::hint::set("SDL_AUDIODRIVER", "dummy");
+            audio_subsystem = match sdl_context.audio().and_then(|audio_subsystem| AudioManager::open_mixer().map(|_| audio_subsystem)){
+                Ok(audio_subsystem) => audio_subsystem,
+                Err(error) => {
+                    println!("Audio disattivato: {}", error);
+                    return audio_manager;
+                }
+            };
+        }
+        sdl2::mixer::allocate_channels(32); // numero di suoni che possono essere riprodotti insieme
+
+        // wav e' sempre supportato, ogg solo se presente il relativo decoder (non obbligatorio)
+        audio_manager._mixer_context = sdl2::mixer::init(InitFlag::OGG).ok();
+        audio_manager._audio_subsystem = Some(audio_subsystem);
+        audio_manager.enabled = true;
+        audio_manager.apply_music_volume();
+
+        audio_manager
+    }
+
+    // 1024 = dimensione del buffer, piu' piccolo = meno latenza per gli effetti
+    fn open_mixer() -> Result<(), String>{
+        sdl2::mixer::open_audio(sdl2::mixer::DEFAULT_FREQUENCY, sdl2::mixer::DEFAULT_FORMAT, sdl2::mixer::DEFAULT_CHANNELS, 1024)
+    }
+
+    pub fn is_enabled(&self) -> bool{
+        self.enabled
+    }
+
+    pub fn set_master_volume(&mut self, volume:f32){
+        self.master_volume = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    pub fn set_sfx_volume(&mut self, volume:f32){
+        self.sfx_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_music_volume(&mut self, volume:f32){
+        self.music_volume = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    fn apply_music_volume(&self){
+        if self.enabled{
+            Music::set_volume((self.master_volume * self.music_volume * MAX_VOLUME as f32) as i32);
+        }
+    }
+
+    // richiede un suono nella posizione indicata (world space), verra' riprodotto alla fine dell'update
+    pub fn play_at(&mut self, sound_name:&str, world_position:FPoint){
+        if self.enabled{
+            self.pending_sounds.push((sound_name.to_string(), world_position));
+        }
+    }
+
+    // riproduce i suoni richiesti nel frame, attenuati in base alla distanza dal centro della camera
+    // e con il panning in base alla posizione orizzontale rispetto al centro dello schermo
+    pub fn flush(&mut self, resource_manager:&ResourceManager, game_utils:&Utils){
+        let half_screen_width = (game_utils.screen_size.0 as f32 / 2.0).max(1.0);
+        let listener_position = game_utils.screen_to_world(FPoint::new(half_screen_width, game_utils.screen_size.1 as f32 / 2.0));
+
+        for (sound_name, world_position) in self.pending_sounds.drain(..){
+            let sound = match resource_manager.get_sound(&sound_name){
+                Some(sound) => sound,
+                None => continue, // suono non caricato
+            };
+
+            let distance = Utils::calculate_point_distance(world_position, listener_position);
+            let attenuation = (1.0 - distance / self.max_distance).clamp(0.0, 1.0);
+            if attenuation <= 0.0{
+                continue;
+            }
+
+            let channel = match Channel::all().play(sound, 0){
+                Ok(channel) => channel,
+                Err(_) => continue, // tutti i canali occupati
+            };
+
+            channel.set_volume((self.master_volume * self.sfx_volume * attenuation * MAX_VOLUME as f32) as i32);
+
+            // pan tra -1.0 (sinistra) e 1.0 (destra)
+            let pan = ((world_position.x - listener_position.x) * game_utils.main_camera_zoom / half_screen_width).clamp(-1.0, 1.0);
+            let left = (255.0 * (1.0 - pan.max(0.0))) as u8;
+            let right = (255.0 * (1.0 + pan.min(0.0))) as u8;
+            let _ = channel.set_panning(left, right);
+        }
+    }
+
+    // musica di sottofondo in loop
+    pub fn play_music(&self, resource_manager:&ResourceManager, music_name:&str) -> Result<(), String>{
+        if !self.enabled{
+            return Ok(());
+        }
+
+        let music = resource_manager.get_music(music_name)
+            .ok_or(format!("Musica {} non caricata", music_name))?;
+        music.play(-1) // -1 -> loop infinito
+    }
+}
+
+impl Drop for AudioManager{
+    fn drop(&mut self){
+        if self.enabled{
+            sdl2::mixer::close_audio();
+        }
+    }
+}
+
+// ------------- VIDEO -------------
+
+// impostazioni della finestra e della risoluzione di rendering
+pub struct VideoSettings{
+    pub logical_size:Option<(u32, u32)>, // None -> si disegna direttamente alla risoluzione della finestra
+    pub integer_scale:bool, // true -> scala solo per multipli interi, false -> letterboxing con scala libera
+    pub fullscreen:bool,
+}
+
+impl VideoSettings{
+    pub fn new(logical_size:Option<(u32, u32)>) -> Self{
+        VideoSettings{
+            logical_size,
+            integer_scale:false,
+            fullscreen:false,
+        }
+    }
+
+    // applica le impostazioni al canvas (e alla sua finestra)
+    pub fn apply(&self, canvas:&mut WindowCanvas) -> Result<(), String>{
+        let fullscreen_type = if self.fullscreen{
+            FullscreenType::Desktop // fullscreen alla risoluzione del desktop, senza cambiare modalita' video
+        }else{
+            FullscreenType::Off
+        };
+        canvas.window_mut().set_fullscreen(fullscreen_type)?;
+
+        match self.logical_size{
+            // con la dimensione logica SDL scala il rendering mantenendo le proporzioni (bande nere ai lati)
+            // e converte anche le coordinate del mouse nella risoluzione logica
+            Some((width, height)) => canvas.set_logical_size(width, height).map_err(|e| e.to_string())?,
+            None => canvas.set_logical_size(0, 0).map_err(|e| e.to_string())?,
+        }
+        canvas.set_integer_scale(self.integer_scale)?;
+
+        Ok(())
+    }
+
+    pub fn toggle_fullscreen(&mut self, canvas:&mut WindowCanvas) -> Result<(), String>{
+        self.fullscreen = !self.fullscreen;
+        self.apply(canvas)
+    }
+}
+
+// ------------- HUD -------------
+
+// punto dello schermo a cui e' agganciato un widget dell'HUD
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HudAnchor{
+    TopLeft,
+    TopCenter,
+    TopRight,
+    Center,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl HudAnchor{
+    // frazione (x, y) della dimensione dello schermo in cui si trova l'ancora.
+    // la stessa frazione applicata alla dimensione del widget da' il suo punto di aggancio (pivot)
+    fn factors(&self) -> (f32, f32){
+        match self{
+            HudAnchor::TopLeft => (0.0, 0.0),
+            HudAnchor::TopCenter => (0.5, 0.0),
+            HudAnchor::TopRight => (1.0, 0.0),
+            HudAnchor::Center => (0.5, 0.5),
+            HudAnchor::BottomLeft => (0.0, 1.0),
+            HudAnchor::BottomCenter => (0.5, 1.0),
+            HudAnchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+// allineamento orizzontale del contenuto quando il box del widget e' piu' largo del contenuto
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HudAlign{
+    Left,
+    Right,
+}
+
+pub enum HudWidgetKind{
+    Label{ text:String, font:(&'static str, u16), color:Color },
+    Bar{ size:(u32, u32), fill:f32, fill_color:Color, background_color:Color }, // fill tra 0.0 e 1.0
+    Icon{ texture:String, source:Option<Rect>, size:(u32, u32) }, // source = None -> tutta la texture
+}
+
+pub struct HudWidget{
+    pub kind:HudWidgetKind,
+    pub anchor:HudAnchor,
+    pub offset:Point, // spostamento rispetto all'ancora (dopo il padding)
+    pub align:HudAlign,
+    pub box_width:Option<u32>, // larghezza fissa del box, None = larga quanto il contenuto
+}
+
+impl HudWidget{
+    fn with_kind(kind:HudWidgetKind) -> Self{
+        HudWidget{
+            kind,
+            anchor:HudAnchor::TopLeft,
+            offset:Point::new(0, 0),
+            align:HudAlign::Left,
+            box_width:None,
+        }
+    }
+
+    pub fn label(text:&str, font:(&'static str, u16), color:Color) -> Self{
+        HudWidget::with_kind(HudWidgetKind::Label { text: text.to_string(), font, color })
+    }
+
+    pub fn bar(size:(u32, u32), fill:f32, fill_color:Color, background_color:Color) -> Self{
+        HudWidget::with_kind(HudWidgetKind::Bar { size, fill: fill.clamp(0.0, 1.0), fill_color, background_color })
+    }
+
+    pub fn icon(texture:&str, source:Option<Rect>, size:(u32, u32)) -> Self{
+        HudWidget::with_kind(HudWidgetKind::Icon { texture: texture.to_string(), source, size })
+    }
+
+    pub fn anchored(mut self, anchor:HudAnchor) -> Self{
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn offset(mut self, offset:Point) -> Self{
+        self.offset = offset;
+        self
+    }
+
+    pub fn aligned(mut self, align:HudAlign, box_width:u32) -> Self{
+        self.align = align;
+        self.box_width = Some(box_width);
+        self
+    }
+}
+
+// layout dell'HUD: calcola la posizione dei widget sulla base della dimensione attuale del canvas,
+// in questo modo l'HUD si adatta a qualsiasi dimensione della finestra
+pub struct Hud{
+    pub padding:i32, // distanza minima dai bordi dello schermo
+}
+
+impl Hud{
+    pub fn new(padding:i32) -> Self{
+        Hud{
+            padding,
+        }
+    }
+
+    // dimensione del contenuto del widget. Per le label si usa Font::size_of, cosi' il testo non viene mai deformato
+    pub fn measure(&self, resource_manager:&ResourceManager, widget:&HudWidget) -> Result<(u32, u32), String>{
+        match &widget.kind{
+            HudWidgetKind::Label { text, font, .. } => resource_manager.measure_text(font.0, font.1, text),
+            HudWidgetKind::Bar { size, .. } => Ok(*size),
+            HudWidgetKind::Icon { size, .. } => Ok(*size),
+        }
+    }
+
+    // rect in cui va disegnato un contenuto di dimensione content_size
+    pub fn layout(&self, screen_size:(u32, u32), widget:&HudWidget, content_size:(u32, u32)) -> Rect{
+        let (anchor_x, anchor_y) = widget.anchor.factors();
+        let box_width = widget.box_width.unwrap_or(content_size.0).max(content_size.0);
+
+        // l'area utile e' lo schermo meno il padding su ogni lato
+        let usable_width = screen_size.0 as f32 - 2.0 * self.padding as f32;
+        let usable_height = screen_size.1 as f32 - 2.0 * self.padding as f32;
+
+        // punto dell'ancora sullo schermo meno il pivot del box
+        let box_x = self.padding as f32 + usable_width * anchor_x - box_width as f32 * anchor_x;
+        let box_y = self.padding as f32 + usable_height * anchor_y - content_size.1 as f32 * anchor_y;
+
+        // allineamento del contenuto all'interno del box
+        let align_factor = match widget.align{
+            HudAlign::Left => 0.0,
+            HudAlign::Right => 1.0,
+        };
+        let content_x = box_x + (box_width - content_size.0) as f32 * align_factor;
+
+        Rect::new(
+            content_x.round() as i32 + widget.offset.x,
+            box_y.round() as i32 + widget.offset.y,
+            content_size.0,
+            content_size.1,
+        )
+    }
+
+    // disegna il widget e restituisce il rect occupato (utile per impilare altri widget sotto/accanto)
+    pub fn draw(&self, canvas:&mut WindowCanvas, resource_manager:&mut ResourceManager, widget:&HudWidget) -> Result<Rect, String>{
+        let content_size = self.measure(resource_manager, widget)?;
+        let target = self.layout(Utils::view_size(canvas)?, widget, content_size);
+
+        match &widget.kind{
+            HudWidgetKind::Label { text, font, color } => {
+                Utils::write_on_screen(text, canvas, resource_manager, *font, *color,
+                    Point::new(target.x(), target.y()), Point::new(target.width() as i32, target.height() as i32))?;
+            },
+            HudWidgetKind::Bar { fill, fill_color, background_color, .. } => {
+                canvas.set_draw_color(*background_color);
+                canvas.fill_rect(target)?;
+
+                let fill_width = (target.width() as f32 * fill).round() as u32;
+                if fill_width > 0{
+                    canvas.set_draw_color(*fill_color);
+                    canvas.fill_rect(Rect::new(target.x(), target.y(), fill_width, target.height()))?;
+                }
+            },
+            HudWidgetKind::Icon { texture, source, .. } => {
+                let icon_texture = resource_manager.get_texture(texture)
+                    .ok_or(format!("Texture {} non caricata", texture))?;
+                canvas.copy(icon_texture, *source, Some(target))?;
+            },
+        }
+
+        Ok(target)
+    }
+}
+
+// ------------- MENU -------------
+
+// menu di pausa con la lista dei comandi e sotto le opzioni on/off: si seleziona una riga con le frecce, Invio per
+// assegnare a un'azione il prossimo tasto / bottone premuto (o per cambiare un'opzione), Tab per aggiungerlo a quelli
+// gia' assegnati, Backspace per tornare al binding di default
+pub struct SettingsMenu{
+    pub open:bool,
+    pub bindings_changed:bool, // impostato quando un binding cambia, Game lo azzera dopo aver salvato il file
+    pub settings_changed:bool, // come bindings_changed, per le opzioni (Game le applica e le salva)
+    selected:usize, // indice in InputAction::ALL, poi le opzioni di GameSettings
+    waiting_for_binding:bool,
+    adding_binding:bool, // il tasto atteso si aggiunge invece di sostituire
+    font:(&'static str, u16),
+    title_font:(&'static str, u16),
+}
+
+impl SettingsMenu{
+    pub fn new(font:(&'static str, u16), title_font:(&'static str, u16)) -> Self{
+        SettingsMenu{
+            open:false,
+            bindings_changed:false,
+            settings_changed:false,
+            selected:0,
+            waiting_for_binding:false,
+            adding_binding:false,
+            font,
+            title_font,
+        }
+    }
+
+    pub fn toggle(&mut self){
+        self.open = !self.open;
+        self.waiting_for_binding = false;
+    }
+
+    // gestisce un evento mentre il menu e' aperto, ritorna true se l'evento e' stato consumato dal menu
+    pub fn manage_event(&mut self, event:&Event, bindings:&mut InputBindings, settings:&mut GameSettings) -> bool{
+        let row_count = InputAction::ALL.len() + GameSettings::TOGGLE_COUNT;
+        // None se la riga selezionata e' un'opzione
+        let selected_action = InputAction::ALL.get(self.selected).copied();
+
+        if let (true, Some(selected_action)) = (self.waiting_for_binding, selected_action){
+            let binding = match event{
+                Event::KeyDown { keycode:Some(Keycode::Escape), .. } => {
+                    self.waiting_for_binding = false; // annullato
+                    return true;
+                },
+                Event::KeyDown { keycode:Some(keycode), repeat:false, .. } => InputBinding::Key(*keycode),
+                Event::MouseButtonDown { mouse_btn, .. } => InputBinding::Mouse(*mouse_btn),
+                Event::ControllerButtonDown { button, .. } => InputBinding::GamepadButton(*button),
+                Event::ControllerAxisMotion { axis, value, .. }
+                    if (*axis == Axis::TriggerLeft || *axis == Axis::TriggerRight) && *value as f32 / GAMEPAD_AXIS_MAX > GAMEPAD_AXIS_PRESS_THRESHOLD => {
+                    InputBinding::GamepadAxis(*axis)
+                },
+                _ => {
+                    return false;
+                }
+            };
+
+            if self.adding_binding{
+                bindings.add_binding(selected_action, binding);
+            }else{
+                bindings.rebind(selected_action, binding);
+            }
+            self.waiting_for_binding = false;
+            self.bindings_changed = true;
+            return true;
+        }
+
+        match event{
+            Event::KeyDown { keycode:Some(Keycode::Up), .. } => {
+                self.selected = (self.selected + row_count - 1) % row_count;
+            },
+            Event::KeyDown { keycode:Some(Keycode::Down), .. } => {
+                self.selected = (self.selected + 1) % row_count;
+            },
+            Event::KeyDown { keycode:Some(Keycode::Tab), repeat:false, .. } if selected_action.is_some() => {
+                self.waiting_for_binding = true;
+                self.adding_binding = true;
+            },
+            Event::KeyDown { keycode:Some(Keycode::Return), repeat:false, .. } => {
+                match selected_action{
+                    Some(_) => {
+                        self.waiting_for_binding = true;
+                        self.adding_binding = false;
+                    },
+                    None => {
+                        settings.flip_toggle(self.selected - InputAction::ALL.len());
+                        self.settings_changed = true;
+                    },
+                }
+            },
+            Event::KeyDown { keycode:Some(Keycode::Backspace), repeat:false, .. } => {
+                if let Some(selected_action) = selected_action{
+                    bindings.reset_to_default(selected_action);
+                    self.bindings_changed = true;
+                }
+            },
+            Event::KeyDown { keycode:Some(Keycode::Escape), .. } => {
+                self.toggle(); // chiude il menu
+            },
+            _ => {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn draw(&self, canvas:&mut WindowCanvas, resource_manager:&mut ResourceManager, hud:&Hud, bindings:&InputBindings,
+        settings:&GameSettings) -> Result<(), String>{
+        let (font, title_font) = (self.font, self.title_font);
+        // sfondo scuro semi trasparente sopra il gioco
+        let (screen_width, screen_height) = Utils::view_size(canvas)?;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
+        canvas.fill_rect(Rect::new(0, 0, screen_width, screen_height))?;
+        canvas.set_blend_mode(BlendMode::None);
+
+        let title = HudWidget::label("PAUSA - COMANDI", title_font, Color::RGB(255, 255, 255))
+            .anchored(HudAnchor::TopCenter)
+            .offset(Point::new(0, 20));
+        hud.draw(canvas, resource_manager, &title)?;
+
+        let row_height = resource_manager.measure_text(font.0, font.1, "A")?.1 as i32 + 4;
+        let first_row_y = -((InputAction::ALL.len() + GameSettings::TOGGLE_COUNT) as i32 * row_height) / 2;
+        let column_width = 220;
+
+        let toggles = (0..GameSettings::TOGGLE_COUNT).map(|index| settings.get_toggle(index));
+        let rows = InputAction::ALL.iter().map(|action| (action.get_name(), Some(*action), false)).chain(toggles.map(|(name, value)| (name, None, value)));
+
+        for (index, (name, action, value)) in rows.enumerate(){
+            let color = if index == self.selected { Color::RGB(255, 220, 0) } else { Color::RGB(255, 255, 255) };
+            let row_y = first_row_y + index as i32 * row_height;
+
+            let bindings_text = match action{
+                Some(_) if index == self.selected && self.waiting_for_binding => "premi un tasto...".to_string(),
+                Some(action) => bindings.get_bindings_text(action),
+                None => String::from(if value { "On" } else { "Off" }),
+            };
+
+            // nome dell'azione allineato a destra a sinistra del centro, tasti allineati a sinistra a destra del centro
+            let action_label = HudWidget::label(name, font, color)
+                .anchored(HudAnchor::Center)
+                .aligned(HudAlign::Right, column_width as u32)
+                .offset(Point::new(-(column_width / 2 + 15), row_y));
+            hud.draw(canvas, resource_manager, &action_label)?;
+
+            let bindings_label = HudWidget::label(if bindings_text.is_empty() { "-" } else { bindings_text.as_str() }, font, color)
+                .anchored(HudAnchor::Center)
+                .aligned(HudAlign::Left, column_width as u32)
+                .offset(Point::new(column_width / 2 + 15, row_y));
+            hud.draw(canvas, resource_manager, &bindings_label)?;
+        }
+
+        // aiuto su due righe, dal basso verso l'alto
+        let help = HudWidget::label("Backspace: default   Esc: chiudi", font, Color::RGB(180, 180, 180))
+            .anchored(HudAnchor::BottomCenter)
+            .offset(Point::new(0, -20));
+        let help_rect = hud.draw(canvas, resource_manager, &help)?;
+
+        let help = HudWidget::label("Frecce: seleziona   Invio: cambia tasto / opzione   Tab: aggiungi tasto", font, Color::RGB(180, 180, 180))
+            .anchored(HudAnchor::BottomCenter)
+            .offset(Point::new(0, -20 - help_rect.height() as i32));
+        hud.draw(canvas, resource_manager, &help)?;
+
+        Ok(())
+    }
+}